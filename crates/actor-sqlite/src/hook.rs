@@ -0,0 +1,44 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use rusqlite::types::Value;
+
+use crate::ActorSqlError;
+
+static QUERY_HOOK: RwLock<Option<Arc<dyn QueryHook>>> = RwLock::new(None);
+
+/// A statement run by a worker.
+#[derive(Debug)]
+pub struct QueryEvent<'e> {
+    pub sql: &'e str,
+    /// `None` if the hook asked for redacted values.
+    pub params: Option<&'e [Value]>,
+    pub elapsed: Duration,
+    /// Affected rows of an exec, returned rows of a query.
+    pub result: Result<usize, &'e ActorSqlError>,
+}
+
+/// Told about every statement run by the workers, see [`set_query_hook`] and
+/// [`PoolConfig::query_hook`](crate::pool_config::PoolConfig::query_hook).
+///
+/// chin-sql sets it along with its own hook, its `WorkerHook` turns a chin-sql `SqlHook`,
+/// e.g. its slow query log, into a `QueryHook`.
+pub trait QueryHook: Send + Sync {
+    /// Keeps the bound values out of the events.
+    fn redact(&self) -> bool {
+        false
+    }
+
+    fn on_query(&self, event: &QueryEvent<'_>);
+}
+
+/// Sets the hook of all pools without an own one.
+pub fn set_query_hook(hook: Option<Arc<dyn QueryHook>>) {
+    *QUERY_HOOK.write().unwrap_or_else(|e| e.into_inner()) = hook;
+}
+
+pub(crate) fn query_hook() -> Option<Arc<dyn QueryHook>> {
+    QUERY_HOOK.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
@@ -1,4 +1,5 @@
 pub mod client;
+pub mod hook;
 mod model;
 pub mod pool;
 pub mod pool_config;
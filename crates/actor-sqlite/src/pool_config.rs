@@ -2,12 +2,14 @@ use flume::Receiver;
 use rusqlite::{Connection, OpenFlags};
 use std::{
     path::{Path, PathBuf},
+    sync::Arc,
     thread,
 };
 
 use crate::{ActorSqlError, Result};
 
 use crate::{
+    hook::QueryHook,
     model::{ConnCmdReq, ConnCmdRsp, RspWrapper},
     worker::ActorSqliteWorker,
 };
@@ -20,6 +22,8 @@ pub struct PoolConfig {
     journal_mode: Option<JournalMode>,
     vfs: Option<String>,
     pub(crate) pool_size: Option<u8>,
+    on_connect: Option<fn(&Connection) -> rusqlite::Result<()>>,
+    query_hook: Option<Arc<dyn QueryHook>>,
 }
 
 /// The possible sqlite journal modes.
@@ -71,12 +75,29 @@ impl PoolConfig {
         }
     }
 
+    /// Runs on every new connection, e.g. to register sql functions.
+    pub fn on_connect(self, on_connect: fn(&Connection) -> rusqlite::Result<()>) -> Self {
+        Self {
+            on_connect: Some(on_connect),
+            ..self
+        }
+    }
+
+    /// Told about every statement of this pool instead of the global hook.
+    pub fn query_hook(self, query_hook: Arc<dyn QueryHook>) -> Self {
+        Self {
+            query_hook: Some(query_hook),
+            ..self
+        }
+    }
+
     pub fn spawn(self, in_rx: Receiver<RspWrapper<ConnCmdReq, ConnCmdRsp>>) -> Result<()> {
+        let hook = self.query_hook.clone();
         let conn = self.build_conn()?;
 
         thread::spawn(move || {
             let conn = conn;
-            ActorSqliteWorker::loop_handle(in_rx, conn);
+            ActorSqliteWorker::loop_handle(in_rx, conn, hook);
         });
 
         Ok(())
@@ -106,6 +127,10 @@ impl PoolConfig {
             }
         }
 
+        if let Some(on_connect) = self.on_connect {
+            on_connect(&conn)?;
+        }
+
         Ok(conn)
     }
 }
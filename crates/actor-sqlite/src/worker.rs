@@ -1,9 +1,13 @@
 use flume::Receiver;
 use log::{debug, error};
 use rusqlite::{Connection, Statement, Transaction, types::Value};
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
-use crate::{ActorSqlError, Result, model::*};
+use crate::{
+    ActorSqlError, Result,
+    hook::{QueryEvent, QueryHook},
+    model::*,
+};
 
 pub(super) struct ActorSqliteWorker;
 
@@ -32,24 +36,40 @@ impl CmdExecutor<'_> {
         }
     }
 
-    fn handle(&self, req: CmdReq) -> Result<CmdResult> {
-        match req {
+    fn handle(&self, req: CmdReq, hook: Option<&dyn QueryHook>) -> Result<CmdResult> {
+        let start = Instant::now();
+        let (sql, params, res) = match req {
             CmdReq::Exec { sql, params } => {
-                let stmt = self.prepare(&sql)?;
-
-                let res = CmdExecutor::handle_exec(stmt, params);
-                res.map(CmdResult::Exec)
+                let res = self
+                    .prepare(&sql)
+                    .and_then(|stmt| CmdExecutor::handle_exec(stmt, &params))
+                    .map(CmdResult::Exec);
+                (sql, params, res)
             }
             CmdReq::QueryMap { sql, params } => {
-                let stmt = self.prepare(&sql)?;
-
-                let res = CmdExecutor::handle_query(stmt, params);
-                res.map(CmdResult::QueryMap)
+                let res = self
+                    .prepare(&sql)
+                    .and_then(|stmt| CmdExecutor::handle_query(stmt, &params))
+                    .map(CmdResult::QueryMap);
+                (sql, params, res)
             }
+        };
+
+        if let Some(hook) = hook {
+            hook.on_query(&QueryEvent {
+                sql: &sql,
+                params: (!hook.redact()).then_some(params.as_slice()),
+                elapsed: start.elapsed(),
+                result: res.as_ref().map(|rsp| match rsp {
+                    CmdResult::Exec(affected) => *affected,
+                    CmdResult::QueryMap(rows) => rows.len(),
+                }),
+            });
         }
+        res
     }
 
-    fn handle_exec(mut stmt: Statement<'_>, params: SqlValueVec) -> Result<usize> {
+    fn handle_exec(mut stmt: Statement<'_>, params: &[Value]) -> Result<usize> {
         let res = stmt.execute(
             params
                 .iter()
@@ -60,7 +80,7 @@ impl CmdExecutor<'_> {
         Ok(res)
     }
 
-    fn handle_query(mut stmt: Statement<'_>, params: SqlValueVec) -> Result<Vec<SVRow>> {
+    fn handle_query(mut stmt: Statement<'_>, params: &[Value]) -> Result<Vec<SVRow>> {
         let columns: Vec<String> = stmt
             .column_names()
             .into_iter()
@@ -94,6 +114,7 @@ impl CmdExecutor<'_> {
 pub(crate) fn conn_run(
     conn: &mut Connection,
     req: RspWrapper<ConnCmdReq, ConnCmdRsp>,
+    hook: Option<&dyn QueryHook>,
 ) -> Result<()> {
     let RspWrapper { command, otx } = req;
     log::debug!("conn run {command:#?}");
@@ -110,9 +131,9 @@ pub(crate) fn conn_run(
             let (tx, rx) = flume::unbounded();
             otx.send(Ok(ConnCmdRsp::Tx(tx)))?;
             debug!("actlite: created tranaction");
-            tx_run(tranaction, rx)?;
+            tx_run(tranaction, rx, hook)?;
         }
-        ConnCmdReq::Command(cmd) => match CmdExecutor::from(conn).handle(cmd) {
+        ConnCmdReq::Command(cmd) => match CmdExecutor::from(conn).handle(cmd, hook) {
             Ok(rsp) => {
                 otx.send(Ok(ConnCmdRsp::Cmd(rsp)))?;
             }
@@ -128,6 +149,7 @@ pub(crate) fn conn_run(
 pub(crate) fn tx_run<'a>(
     tx: Transaction<'a>,
     rx: Receiver<RspWrapper<TxCmdReq, TxCmdRsp>>,
+    hook: Option<&dyn QueryHook>,
 ) -> Result<()> {
     let executor = CmdExecutor::from(&tx);
     loop {
@@ -135,7 +157,7 @@ pub(crate) fn tx_run<'a>(
         let RspWrapper { command, otx } = rx.recv()?;
         log::debug!("transaction run {command:#?}");
         match command {
-            TxCmdReq::Command(cmd) => match executor.handle(cmd) {
+            TxCmdReq::Command(cmd) => match executor.handle(cmd, hook) {
                 Ok(rsp) => {
                     debug!("actlite: transaction execute done {rsp:?}");
                     otx.send(Ok(TxCmdRsp::Cmd(rsp)))?;
@@ -179,11 +201,14 @@ impl ActorSqliteWorker {
     pub(crate) fn loop_handle(
         in_rx: flume::Receiver<RspWrapper<ConnCmdReq, ConnCmdRsp>>,
         mut conn: Connection,
+        hook: Option<Arc<dyn QueryHook>>,
     ) {
         loop {
             match in_rx.recv() {
                 Ok(cb) => {
-                    let result = conn_run(&mut conn, cb);
+                    // the hook of the pool wins over the global one
+                    let hook = hook.clone().or_else(crate::hook::query_hook);
+                    let result = conn_run(&mut conn, cb, hook.as_deref());
                     match result {
                         Ok(_) => {}
                         Err(err) => {
@@ -19,3 +19,58 @@ async fn test1() {
         println!("error: {r:?}")
     }
 }
+
+#[tokio::test]
+async fn query_hook() {
+    use std::sync::{Arc, Mutex};
+
+    use actor_sqlite::hook::{QueryEvent, QueryHook};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, Option<usize>, bool)>>);
+
+    impl QueryHook for Recorder {
+        fn on_query(&self, event: &QueryEvent<'_>) {
+            self.0.lock().unwrap().push((
+                event.sql.to_owned(),
+                event.result.as_ref().ok().copied(),
+                event.params.is_some(),
+            ));
+        }
+    }
+
+    let recorder = Arc::new(Recorder::default());
+    let pool = actor_sqlite::pool::ActorSqlitePool::try_from(
+        PoolConfig::default()
+            .path(":memory:")
+            .query_hook(recorder.clone()),
+    )
+    .unwrap();
+    let mut client = pool.get().await.unwrap();
+    client
+        .execute("create table hook(id integer)", vec![])
+        .await
+        .unwrap();
+    let tx = client.transaction().await.unwrap();
+    tx.execute(
+        "insert into hook values (?), (?)".to_owned(),
+        vec![1.into(), 2.into()],
+    )
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    let rows = client.query("select id from hook", vec![]).await.unwrap();
+    assert_eq!(2, rows.len());
+    assert!(client.query("select nope", vec![]).await.is_err());
+
+    let events = recorder.0.lock().unwrap();
+    assert_eq!(
+        vec![
+            ("create table hook(id integer)".to_owned(), Some(0), true),
+            ("insert into hook values (?), (?)".to_owned(), Some(2), true),
+            ("select id from hook".to_owned(), Some(2), true),
+            ("select nope".to_owned(), None, true),
+        ],
+        *events
+    );
+}
@@ -4,7 +4,18 @@ mod table_schema;
 
 #[proc_macro_derive(
     GenerateTableSchema,
-    attributes(gts_primary, gts_type, gts_key, gts_unique, gts_tosql)
+    attributes(
+        gts_primary,
+        gts_type,
+        gts_key,
+        gts_unique,
+        gts_tosql,
+        gts_created_at,
+        gts_updated_at,
+        gts_soft_delete,
+        gts_version,
+        gts_tenant
+    )
 )]
 pub fn generate_table_schema(input: TokenStream) -> TokenStream {
     table_schema::generate_table_schema(input)
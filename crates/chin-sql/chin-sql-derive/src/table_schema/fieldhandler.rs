@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use chin_sql::LogicFieldType;
+use chin_sql::str_type::LengthUnit;
 use quote::ToTokens;
 use syn::spanned::Spanned;
 use syn::{Field, PathArguments, Type, TypePath};
@@ -20,6 +21,14 @@ impl KeyOrder {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AuditKind {
+    CreatedAt,
+    UpdatedAt,
+    SoftDelete,
+    Version,
+}
+
 #[derive(Debug)]
 pub(crate) struct FieldInfo {
     pub column_name: String,
@@ -28,6 +37,8 @@ pub(crate) struct FieldInfo {
     pub key_map: HashMap<String, (bool, KeyOrder)>, // key_name(lower), unique?, keyorder
     pub pkey: Option<KeyOrder>,
     pub to_sql_func: Option<String>,
+    pub audit: Option<AuditKind>,
+    pub tenant: bool,
 }
 
 pub(crate) fn parse_field_info(field: &Field) -> Result<FieldInfo, syn::Error> {
@@ -60,6 +71,8 @@ pub(crate) fn parse_field_info(field: &Field) -> Result<FieldInfo, syn::Error> {
     let pkey = find_pkey(field)?;
     let key_map = find_attr_key(&column_name, field)?;
     let to_sql_func = find_to_sql_func(field)?;
+    let audit = find_audit(field)?;
+    let tenant = field.attrs.iter().any(|a| a.path().is_ident("gts_tenant"));
 
     Ok(FieldInfo {
         column_name,
@@ -68,6 +81,8 @@ pub(crate) fn parse_field_info(field: &Field) -> Result<FieldInfo, syn::Error> {
         key_map,
         pkey,
         to_sql_func,
+        audit,
+        tenant,
     })
 }
 
@@ -109,11 +124,12 @@ fn parse_field_type(
             "DateTime<Utc>" => chin_sql::LogicFieldType::Timestamp,
             rt => {
                 if rt.starts_with("Varchar<") && rt.ends_with(">") {
-                    let text = &rt[8..(rt.len() - 1)];
+                    let inner = &rt[8..(rt.len() - 1)];
+                    let (text, policy) = inner.split_once(',').unwrap_or((inner, "Chars"));
                     let bound = text.parse::<u16>().map_err(|err| {
                         syn::Error::new(field.span(), format!("{text} in `{rt}` is illegal, {err}"))
                     })?;
-                    chin_sql::LogicFieldType::Varchar(bound)
+                    chin_sql::LogicFieldType::Varchar(bound, parse_length_unit(field, policy)?)
                 } else {
                     Err(syn::Error::new(
                         field.span(),
@@ -137,6 +153,23 @@ fn parse_field_type(
     }
 }
 
+// Policy is `Bytes`, `Chars`, `Graphemes` or `Truncate<...>` of them, maybe with a path.
+fn parse_length_unit(field: &Field, policy: &str) -> Result<LengthUnit, syn::Error> {
+    let policy = policy
+        .strip_suffix('>')
+        .and_then(|p| p.rsplit_once("Truncate<"))
+        .map_or(policy, |(_, p)| p);
+    match policy.rsplit("::").next().unwrap_or(policy) {
+        "Bytes" => Ok(LengthUnit::Bytes),
+        "Chars" => Ok(LengthUnit::Chars),
+        "Graphemes" => Ok(LengthUnit::Graphemes),
+        other => Err(syn::Error::new(
+            field.span(),
+            format!("Unkown Varchar policy {other:#?}"),
+        )),
+    }
+}
+
 pub(crate) fn find_attr_key(
     column_name: &str,
     field: &Field,
@@ -196,6 +229,31 @@ fn find_pkey(field: &Field) -> Result<Option<KeyOrder>, syn::Error> {
     Ok(None)
 }
 
+fn find_audit(field: &Field) -> Result<Option<AuditKind>, syn::Error> {
+    let mut audit = None;
+    for attr in &field.attrs {
+        let kind = if attr.path().is_ident("gts_created_at") {
+            AuditKind::CreatedAt
+        } else if attr.path().is_ident("gts_updated_at") {
+            AuditKind::UpdatedAt
+        } else if attr.path().is_ident("gts_soft_delete") {
+            AuditKind::SoftDelete
+        } else if attr.path().is_ident("gts_version") {
+            AuditKind::Version
+        } else {
+            continue;
+        };
+        if audit.replace(kind).is_some() {
+            return Err(syn::Error::new(
+                attr.span(),
+                "only one of gts_created_at, gts_updated_at, gts_soft_delete and gts_version is allowed",
+            ));
+        }
+    }
+
+    Ok(audit)
+}
+
 fn find_to_sql_func(field: &Field) -> Result<Option<String>, syn::Error> {
     for attr in &field.attrs {
         if attr.path().is_ident("gts_tosql") {
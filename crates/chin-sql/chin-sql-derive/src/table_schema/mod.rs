@@ -9,7 +9,7 @@ use quote::{format_ident, quote};
 use syn::spanned::Spanned;
 use syn::{Data, DeriveInput, Field, Fields, parse_macro_input};
 
-use crate::table_schema::fieldhandler::{FieldInfo, KeyOrder};
+use crate::table_schema::fieldhandler::{AuditKind, FieldInfo, KeyOrder};
 
 pub(crate) fn generate_table_schema(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -136,22 +136,32 @@ fn generate_inner(
     table_name: &str,
     fields: &Vec<(FieldInfo, &Field)>,
 ) -> Result<TokenStream2, syn::Error> {
-    let inserter = to_sql_inserter(fields);
+    let inserter = to_sql_inserter(fields, fields.iter().any(|(fi, _)| fi.tenant));
+    let audit = TableAudit::new(fields)?;
 
     let mut column_structs = TokenStream2::new();
     let mut all_fields = TokenStream2::new();
+    let mut tenant_fields = TokenStream2::new();
     for (fi, _) in fields {
         let column_name = &fi.column_name;
+        if fi.tenant {
+            tenant_fields.extend(quote! {#column_name, });
+        }
         let not_null = fi.not_null;
         let sql_type = &match fi.field_type {
             chin_sql::LogicFieldType::Bool => quote! { chin_sql::LogicFieldType::Bool },
             chin_sql::LogicFieldType::I8 => quote! { chin_sql::LogicFieldType::I8 },
             chin_sql::LogicFieldType::I16 => quote! { chin_sql::LogicFieldType::I16 },
-            chin_sql::LogicFieldType::I32 => quote! { chin_sql::LogicFieldType::I3 },
+            chin_sql::LogicFieldType::I32 => quote! { chin_sql::LogicFieldType::I32 },
             chin_sql::LogicFieldType::I64 => quote! { chin_sql::LogicFieldType::I64 },
             chin_sql::LogicFieldType::F64 => quote! { chin_sql::LogicFieldType::F64 },
-            chin_sql::LogicFieldType::Varchar(c) => {
-                quote! { chin_sql::LogicFieldType::Varchar(#c) }
+            chin_sql::LogicFieldType::Varchar(c, unit) => {
+                let unit = match unit {
+                    chin_sql::str_type::LengthUnit::Bytes => quote! { Bytes },
+                    chin_sql::str_type::LengthUnit::Chars => quote! { Chars },
+                    chin_sql::str_type::LengthUnit::Graphemes => quote! { Graphemes },
+                };
+                quote! { chin_sql::LogicFieldType::Varchar(#c, chin_sql::str_type::LengthUnit::#unit) }
             }
             chin_sql::LogicFieldType::Text => quote! { chin_sql::LogicFieldType::Text },
             chin_sql::LogicFieldType::Blob => quote! { chin_sql::LogicFieldType::Blob },
@@ -198,6 +208,7 @@ fn generate_inner(
     }
 
     let mut functions = TokenStream2::new();
+    let tenant = !tenant_fields.is_empty();
 
     let mut pkey_schema = TokenStream2::new();
     for (fi, _) in &pkey_fields {
@@ -205,7 +216,7 @@ fn generate_inner(
         pkey_schema.extend(quote! { #cn });
         pkey_schema.extend(quote! {, });
     }
-    functions.extend(key_func("pkey", &pkey_fields));
+    functions.extend(key_func("pkey", &pkey_fields, &audit, true, tenant));
 
     let mut unikey_map = HashMap::new();
     let mut key_map = HashMap::new();
@@ -239,7 +250,13 @@ fn generate_inner(
                 format!("KeyOrder should be the same, {key}"),
             ));
         }
-        functions.extend(key_func(format!("unikey_{key}").as_str(), &fs));
+        functions.extend(key_func(
+            format!("unikey_{key}").as_str(),
+            &fs,
+            &audit,
+            true,
+            tenant,
+        ));
         let fss: Vec<String> = fs.iter().map(|f| f.0.column_name.clone()).collect();
         let fss = fss.join(", ");
         let key_name = format!("ukey_{key}");
@@ -258,14 +275,36 @@ fn generate_inner(
                 format!("KeyOrder should be the same, {key}"),
             ));
         }
-        functions.extend(key_func(format!("key_{key}").as_str(), &fs));
+        functions.extend(key_func(
+            format!("key_{key}").as_str(),
+            &fs,
+            &audit,
+            false,
+            tenant,
+        ));
         let fss: Vec<String> = fs.iter().map(|f| f.0.column_name.clone()).collect();
         let fss = fss.join(", ");
         let key_name = format!("key_{key}");
         key_schema.extend(quote! {  ( #key_name, &[#fss]), });
     }
 
+    // the derived builders know the tenant columns, registering them also checks queries
+    // written by hand
+    let tenant = if tenant {
+        quote! {
+            pub const TENANT_COLUMNS: &'static [&'static str] = &[ #tenant_fields ];
+
+            pub fn register_tenant() {
+                chin_sql::register_tenant_table(Self::TABLE, Self::TENANT_COLUMNS);
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+
     Ok(quote! {
+        #tenant
+
         #[inline]
         pub fn create_sql() -> &'static chin_sql::CreateTableSql {
             &chin_sql::CreateTableSql {
@@ -273,7 +312,8 @@ fn generate_inner(
                 fields: &[ #column_structs ],
                 pkey: &[ #pkey_schema ],
                 unikeys: &[ #unikey_schema ],
-                keys: &[ #key_schema ]
+                keys: &[ #key_schema ],
+                tenant: &[ #tenant_fields ],
             }
         }
 
@@ -309,14 +349,80 @@ fn camel2snake(name: &str) -> String {
     table_name
 }
 
-fn to_sql_inserter(fields: &Vec<(FieldInfo, &Field)>) -> TokenStream2 {
+// Columns maintained by the generated functions, found by the gts_updated_at,
+// gts_soft_delete and gts_version attributes.
+struct TableAudit<'f> {
+    updated_at: Option<(&'f FieldInfo, &'f Field)>,
+    soft_delete: Option<(&'f FieldInfo, &'f Field)>,
+    version: Option<(&'f FieldInfo, &'f Field)>,
+}
+
+impl<'f> TableAudit<'f> {
+    fn new(fields: &'f [(FieldInfo, &'f Field)]) -> Result<Self, syn::Error> {
+        let find = |kind: AuditKind| {
+            let mut found = fields.iter().filter(|(fi, _)| fi.audit == Some(kind));
+            let first = found.next().map(|(fi, f)| (fi, *f));
+            match found.next() {
+                Some((_, f)) => Err(syn::Error::new(
+                    f.span(),
+                    format!("{kind:?} can only be set on one field"),
+                )),
+                None => Ok(first),
+            }
+        };
+        find(AuditKind::CreatedAt)?;
+        Ok(Self {
+            updated_at: find(AuditKind::UpdatedAt)?,
+            soft_delete: find(AuditKind::SoftDelete)?,
+            version: find(AuditKind::Version)?,
+        })
+    }
+
+    // the filter that hides soft deleted rows
+    fn alive(&self) -> TokenStream2 {
+        match self.soft_delete {
+            Some((fi, f)) => {
+                let column = format_ident!("{}", fi.column_name.to_uppercase());
+                let ty = &f.ty;
+                quote! { <#ty as chin_sql::SoftDeleteMark>::alive(Self::#column), }
+            }
+            None => TokenStream2::new(),
+        }
+    }
+
+    fn touch(&self) -> TokenStream2 {
+        match self.updated_at {
+            Some((fi, f)) => {
+                let column = format_ident!("{}", fi.column_name.to_uppercase());
+                let ty = &f.ty;
+                quote! { .set(Self::#column, <#ty as chin_sql::AuditTimestamp>::now()) }
+            }
+            None => TokenStream2::new(),
+        }
+    }
+}
+
+fn to_sql_inserter(fields: &Vec<(FieldInfo, &Field)>, tenant: bool) -> TokenStream2 {
     let mut func_stream = TokenStream2::default();
     for (fi, f) in fields.iter() {
         let db_field_ident = format_ident!("{}", fi.column_name.to_uppercase());
         let Some(field_indent) = f.ident.clone() else {
             return syn::Error::new(f.span(), "this field has no ident").to_compile_error();
         };
-        if let Some(mp) = fi.to_sql_func.as_ref() {
+        if matches!(
+            fi.audit,
+            Some(AuditKind::CreatedAt) | Some(AuditKind::UpdatedAt)
+        ) {
+            let ty = &f.ty;
+            func_stream.extend(
+                quote! { .field(Self::#db_field_ident, <#ty as chin_sql::AuditTimestamp>::now()) },
+            );
+        } else if fi.audit == Some(AuditKind::Version) {
+            let ty = &f.ty;
+            func_stream.extend(
+                quote! { .field(Self::#db_field_ident, <#ty as ::core::convert::From<u8>>::from(1)) },
+            );
+        } else if let Some(mp) = fi.to_sql_func.as_ref() {
             let mp = format_ident!("{}", mp);
             func_stream.extend(quote! { .field(Self::#db_field_ident, #mp(self.#field_indent)) });
         } else {
@@ -324,6 +430,10 @@ fn to_sql_inserter(fields: &Vec<(FieldInfo, &Field)>) -> TokenStream2 {
         }
     }
 
+    if tenant {
+        func_stream.extend(quote! { .tenant_columns(Self::TENANT_COLUMNS) });
+    }
+
     quote! {
         pub fn to_sql_inserter(self) -> chin_sql::SqlInserter<'static> {
             chin_sql::SqlInserter::new(Self::TABLE)
@@ -332,7 +442,13 @@ fn to_sql_inserter(fields: &Vec<(FieldInfo, &Field)>) -> TokenStream2 {
     }
 }
 
-fn key_func(prefix: &str, fields: &Vec<(&FieldInfo, &Field)>) -> TokenStream2 {
+fn key_func(
+    prefix: &str,
+    fields: &Vec<(&FieldInfo, &Field)>,
+    audit: &TableAudit,
+    unique: bool,
+    tenant: bool,
+) -> TokenStream2 {
     let mut args = TokenStream2::default();
     let mut wheres = TokenStream2::default();
     let len = fields.len();
@@ -352,28 +468,110 @@ fn key_func(prefix: &str, fields: &Vec<(&FieldInfo, &Field)>) -> TokenStream2 {
     let reader = format_ident!("{}_reader", prefix);
     let updater = format_ident!("{}_updater", prefix);
     let where_cond = format_ident!("{}_cond", prefix);
+    let alive = audit.alive();
+    let touch = audit.touch();
+
+    // every write bumps the version, unique updaters only hit the row when it still has
+    // the version read by the caller
+    let mut updater_args = args.clone();
+    let mut updater_wheres = wheres.clone();
+    let mut bump = TokenStream2::new();
+    if let Some((fi, f)) = audit.version {
+        let column = format_ident!("{}", fi.column_name.to_uppercase());
+        bump = quote! { .increment(Self::#column) };
+        if unique {
+            let ty = &f.ty;
+            if !fields.is_empty() {
+                updater_args.extend(quote! {, });
+            }
+            updater_args.extend(quote! { expected_version: #ty });
+            updater_wheres
+                .extend(quote! { chin_sql::Wheres::equal(Self::#column, expected_version), });
+        }
+    }
 
-    let expanded = quote! {
+    // tenant tables fail to build without a scope, their conditions take it as argument
+    let mut cond_args = args.clone();
+    let mut cond_ret = quote! { chin_sql::Wheres<'c> };
+    let mut cond_wheres = wheres.clone();
+    let mut cond_ok = TokenStream2::new();
+    let mut reader_tenant = TokenStream2::new();
+    let mut updater_tenant = TokenStream2::new();
+    if tenant {
+        if !fields.is_empty() {
+            cond_args.extend(quote! {, });
+        }
+        cond_args.extend(quote! { scope: &chin_sql::TenantScope<'c> });
+        cond_ret = quote! { Result<chin_sql::Wheres<'c>, chin_sql::ChinSqlError> };
+        cond_wheres
+            .extend(quote! { scope.columns_wheres(Self::TABLE, Self::TENANT_COLUMNS, None)?, });
+        cond_ok = quote! { Ok };
+        reader_tenant = quote! { .tenant_columns(Self::TABLE, Self::TENANT_COLUMNS) };
+        updater_tenant = quote! { .tenant_columns(Self::TENANT_COLUMNS) };
+    }
+
+    let mut expanded = quote! {
         pub fn #reader<'a>(#args) -> chin_sql::SqlBuilder<'a> {
             chin_sql::SqlBuilder::read_all(Self::TABLE)
             .r#where(chin_sql::Wheres::and([
                 #wheres
+                #alive
             ]))
+            #reader_tenant
         }
 
-        pub fn #updater<'c>(#args) -> chin_sql::SqlUpdater<'c> {
+        pub fn #updater<'c>(#updater_args) -> chin_sql::SqlUpdater<'c> {
             chin_sql::SqlUpdater::new(Self::TABLE)
+            #touch
+            #bump
             .r#where(chin_sql::Wheres::and([
-                #wheres
+                #updater_wheres
             ]))
+            #updater_tenant
         }
 
-        pub fn #where_cond<'c>(#args) -> chin_sql::Wheres<'c> {
-            chin_sql::Wheres::and([
-                #wheres
-            ])
+        pub fn #where_cond<'c>(#cond_args) -> #cond_ret {
+            #cond_ok(chin_sql::Wheres::and([
+                #cond_wheres
+                #alive
+            ]))
         }
     };
 
+    if let Some((fi, f)) = audit.soft_delete {
+        let reader_all = format_ident!("{}_reader_with_deleted", prefix);
+        let cond_all = format_ident!("{}_cond_with_deleted", prefix);
+        let deleter = format_ident!("{}_soft_deleter", prefix);
+        let column = format_ident!("{}", fi.column_name.to_uppercase());
+        let ty = &f.ty;
+        expanded.extend(quote! {
+            pub fn #reader_all<'a>(#args) -> chin_sql::SqlBuilder<'a> {
+                chin_sql::SqlBuilder::read_all(Self::TABLE)
+                .r#where(chin_sql::Wheres::and([
+                    #wheres
+                ]))
+                #reader_tenant
+            }
+
+            pub fn #cond_all<'c>(#cond_args) -> #cond_ret {
+                #cond_ok(chin_sql::Wheres::and([
+                    #cond_wheres
+                ]))
+            }
+
+            pub fn #deleter<'c>(#args) -> chin_sql::SqlUpdater<'c> {
+                chin_sql::SqlUpdater::new(Self::TABLE)
+                .set(Self::#column, <#ty as chin_sql::SoftDeleteMark>::deleted())
+                #touch
+                #bump
+                .r#where(chin_sql::Wheres::and([
+                    #wheres
+                    #alive
+                ]))
+                #updater_tenant
+            }
+        });
+    }
+
     expanded
 }
@@ -1,5 +1,5 @@
-use chin_sql::DbType;
-use chin_sql::str_type::Varchar;
+use chin_sql::str_type::{Bytes, Varchar};
+use chin_sql::{DbType, IntoSqlSeg};
 use chin_sql_derive::GenerateTableSchema;
 use chrono::DateTime;
 use chrono::FixedOffset;
@@ -15,6 +15,8 @@ struct ExampleTable {
     #[gts_type = "bool"]
     #[gts_unique]
     create_at2: Option<DateTime<FixedOffset>>,
+
+    code: Option<Varchar<16, Bytes>>,
 }
 
 #[test]
@@ -32,4 +34,237 @@ fn table_generate() {
     assert_eq!("example_table", ExampleTable::TABLE);
 
     assert_eq!("create_at", ExampleTable::CREATE_AT);
+
+    let sqls = ExampleTable::create_sql()
+        .to_owned_sql()
+        .sqls(DbType::Postgres)
+        .unwrap();
+    assert!(sqls[0].contains("id Varchar(211) not null"));
+    assert!(sqls[0].contains("code TEXT check (octet_length(code) <= 16)"));
+    assert!(sqls[0].contains("create_at TIMESTAMPTZ, create_at2 BOOL, code"));
+
+    let sqls = ExampleTable::create_sql()
+        .to_owned_sql()
+        .sqls(DbType::DuckDb)
+        .unwrap();
+    assert!(sqls[0].contains("id VARCHAR not null"));
+    assert!(sqls[0].contains("create_at TIMESTAMPTZ"));
+    assert!(sqls[0].contains("code VARCHAR check (octet_length(code) <= 16)"));
+}
+
+#[allow(dead_code)]
+#[derive(GenerateTableSchema)]
+struct AuditTable {
+    #[gts_primary]
+    id: i64,
+
+    #[gts_key = "name"]
+    name: Varchar<32>,
+
+    #[gts_created_at]
+    created_at: DateTime<FixedOffset>,
+
+    #[gts_updated_at]
+    updated_at: DateTime<FixedOffset>,
+
+    #[gts_soft_delete]
+    deleted_at: Option<DateTime<FixedOffset>>,
+}
+
+#[test]
+fn audit_columns() {
+    let reader = AuditTable::pkey_reader(1)
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(reader.seg.contains("deleted_at is null"));
+
+    let reader = AuditTable::pkey_reader_with_deleted(1)
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(!reader.seg.contains("deleted_at"));
+
+    let updater = AuditTable::pkey_updater(1)
+        .set(AuditTable::NAME, "n")
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(updater.seg.contains("updated_at = ?"));
+
+    let deleter = AuditTable::key_name_soft_deleter("n".try_into().unwrap())
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(deleter.seg.contains("deleted_at = ?"));
+    assert!(deleter.seg.contains("deleted_at is null"));
+    assert_eq!(3, deleter.values.len());
+}
+
+#[allow(dead_code)]
+#[derive(GenerateTableSchema)]
+struct VersionedTable {
+    #[gts_primary]
+    id: i64,
+
+    #[gts_unique = "code"]
+    code: Varchar<16>,
+
+    #[gts_version]
+    version: i32,
+
+    #[gts_key = "state"]
+    state: i32,
+}
+
+#[test]
+fn version_columns() {
+    let updater = VersionedTable::pkey_updater(1, 3)
+        .set(VersionedTable::CODE, "c")
+        .into_sql_seg(DbType::Postgres)
+        .unwrap();
+    assert!(updater.seg.contains("version = version + 1"));
+    assert!(updater.seg.contains("version = $3"));
+    assert_eq!(3, updater.values.len());
+
+    let updater = VersionedTable::unikey_code_updater("c".try_into().unwrap(), 3)
+        .set(VersionedTable::CODE, "d")
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(updater.seg.contains("version = ?"));
+
+    // non unique updaters have no expected version but still bump it
+    let updater = VersionedTable::key_state_updater(2)
+        .set(VersionedTable::STATE, 3)
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(updater.seg.contains("version = version + 1"));
+    assert!(!updater.seg.contains("version = ?"));
+
+    let inserter = VersionedTable {
+        id: 1,
+        code: "c".try_into().unwrap(),
+        version: 7,
+        state: 0,
+    }
+    .to_sql_inserter()
+    .into_sql_seg(DbType::Sqlite)
+    .unwrap();
+    assert!(matches!(inserter.values[2], chin_sql::SqlValue::I32(1)));
+
+    assert!(matches!(
+        chin_sql::check_version(VersionedTable::TABLE, 0),
+        Err(chin_sql::ChinSqlError::VersionConflict(_))
+    ));
+    assert_eq!(
+        1,
+        chin_sql::check_version(VersionedTable::TABLE, 1).unwrap()
+    );
+}
+
+#[allow(dead_code)]
+#[derive(GenerateTableSchema)]
+struct TenantNote {
+    #[gts_primary]
+    id: i64,
+
+    #[gts_tenant]
+    tenant_id: i64,
+
+    #[gts_key = "title"]
+    title: Varchar<32>,
+}
+
+#[test]
+fn tenant_columns() {
+    // the derived builders fail without a scope even if the table is never registered
+    assert_eq!(&["tenant_id"], TenantNote::TENANT_COLUMNS);
+    assert_eq!(&["tenant_id"], TenantNote::create_sql().tenant);
+
+    assert!(matches!(
+        TenantNote::pkey_reader(1).into_sql_seg(DbType::Sqlite),
+        Err(chin_sql::ChinSqlError::MissingTenantScope(_))
+    ));
+    assert!(matches!(
+        TenantNote::pkey_updater(1)
+            .set(TenantNote::TITLE, "u")
+            .into_sql_seg(DbType::Sqlite),
+        Err(chin_sql::ChinSqlError::MissingTenantScope(_))
+    ));
+    let note = |tenant_id| TenantNote {
+        id: 1,
+        tenant_id,
+        title: "t".try_into().unwrap(),
+    };
+    assert!(matches!(
+        note(7).to_sql_inserter().into_sql_seg(DbType::Sqlite),
+        Err(chin_sql::ChinSqlError::MissingTenantScope(_))
+    ));
+
+    let scope = chin_sql::TenantScope::new().column("tenant_id", 7_i64);
+    let reader = TenantNote::key_title_reader("t".try_into().unwrap())
+        .scope(&scope)
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert_eq!(
+        "select * from tenant_note  where (title = ?) and tenant_note.tenant_id = ? ",
+        reader.seg
+    );
+    let updater = TenantNote::pkey_updater(1)
+        .set(TenantNote::TITLE, "u")
+        .scope(&scope)
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(updater.seg.contains("tenant_note.tenant_id = ?"));
+
+    let cond = TenantNote::pkey_cond(1, &scope).unwrap();
+    assert!(format!("{cond:?}").contains("tenant_id"));
+    assert!(TenantNote::pkey_cond(1, &chin_sql::TenantScope::new()).is_err());
+
+    let inserter = note(7)
+        .to_sql_inserter()
+        .scope(&scope)
+        .into_sql_seg(DbType::Sqlite)
+        .unwrap();
+    assert!(matches!(inserter.values[1], chin_sql::SqlValue::I64(7)));
+    // a row of another tenant is not silently moved into the scope
+    assert!(
+        note(8)
+            .to_sql_inserter()
+            .scope(&scope)
+            .into_sql_seg(DbType::Sqlite)
+            .is_err()
+    );
+}
+
+#[allow(dead_code)]
+#[derive(GenerateTableSchema)]
+struct PlanNote {
+    #[gts_primary]
+    id: i64,
+
+    #[gts_key = "title"]
+    title: Varchar<32>,
+
+    body: Varchar<256>,
+}
+
+#[tokio::test]
+async fn key_reader_plan() {
+    use actor_sqlite::{pool::ActorSqlitePool, pool_config::PoolConfig};
+    use chin_sql::{QueryPlan, SqlExecutor};
+
+    let pool = ActorSqlitePool::try_from(PoolConfig::default().path(":memory:")).unwrap();
+    let client = pool.get().await.unwrap();
+    for sql in PlanNote::create_sql()
+        .to_owned_sql()
+        .sqls(DbType::Sqlite)
+        .unwrap()
+    {
+        SqlExecutor::execute(&client, sql).await.unwrap();
+    }
+
+    let reader = PlanNote::key_title_reader("t".try_into().unwrap());
+    let plan = QueryPlan::explain(&client, reader.into_sql_seg(DbType::Sqlite).unwrap())
+        .await
+        .unwrap();
+    assert!(plan.uses_index("plan_note_key_title"), "{plan}");
+    assert!(plan.full_scans().is_empty(), "{plan}");
+    assert!(plan.temp_btrees().is_empty(), "{plan}");
 }
@@ -0,0 +1,76 @@
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::{ChinSqlError, Wheres, time_type::TID, time_type::current_timestamptz};
+
+/// Types of `gts_created_at` / `gts_updated_at` columns.
+pub trait AuditTimestamp: Sized {
+    fn now() -> Self;
+}
+
+impl AuditTimestamp for DateTime<FixedOffset> {
+    fn now() -> Self {
+        current_timestamptz()
+    }
+}
+
+impl AuditTimestamp for DateTime<Utc> {
+    fn now() -> Self {
+        Utc::now()
+    }
+}
+
+impl AuditTimestamp for TID {
+    fn now() -> Self {
+        Utc::now().into()
+    }
+}
+
+/// Milliseconds since epoch.
+impl AuditTimestamp for i64 {
+    fn now() -> Self {
+        Utc::now().timestamp_millis()
+    }
+}
+
+impl<T: AuditTimestamp> AuditTimestamp for Option<T> {
+    fn now() -> Self {
+        Some(T::now())
+    }
+}
+
+/// Types of `gts_soft_delete` columns, either a nullable timestamp or a bool flag.
+pub trait SoftDeleteMark: Sized {
+    /// Value written when a row is soft deleted.
+    fn deleted() -> Self;
+
+    /// Filter which keeps rows that are not soft deleted.
+    fn alive(column: &str) -> Wheres<'_>;
+}
+
+impl<T: AuditTimestamp> SoftDeleteMark for Option<T> {
+    fn deleted() -> Self {
+        Some(T::now())
+    }
+
+    fn alive(column: &str) -> Wheres<'_> {
+        Wheres::is_null(column)
+    }
+}
+
+impl SoftDeleteMark for bool {
+    fn deleted() -> Self {
+        true
+    }
+
+    fn alive(column: &str) -> Wheres<'_> {
+        Wheres::equal(column, false)
+    }
+}
+
+/// A versioned update touching no row lost the race with another writer.
+pub fn check_version(table: &str, affected: usize) -> Result<usize, ChinSqlError> {
+    match affected {
+        0 => Err(ChinSqlError::VersionConflict(table.to_owned())),
+        n => Ok(n),
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{IntoSqlSeg, LogicFieldType, SqlBuilder};
+use crate::{IntoSqlSeg, LogicFieldType, SqlBuilder, str_type::LengthUnit};
 
 #[derive(Clone, Debug)]
 pub struct CreateTableField {
@@ -14,6 +14,8 @@ pub struct CreateTableSql {
     pub pkey: &'static [&'static str],
     pub unikeys: &'static [(&'static str, &'static [&'static str])],
     pub keys: &'static [(&'static str, &'static [&'static str])],
+    /// Tenant columns, queries of the derived builders fail without a [`crate::TenantScope`].
+    pub tenant: &'static [&'static str],
 }
 
 impl CreateTableSql {
@@ -40,7 +42,7 @@ impl LogicFieldType {
                 LogicFieldType::I32 => "INTEGER".into(),
                 LogicFieldType::I64 => "INTEGER".into(),
                 LogicFieldType::F64 => "REAL".into(),
-                LogicFieldType::Varchar(_) => "TEXT".into(),
+                LogicFieldType::Varchar(..) => "TEXT".into(),
                 LogicFieldType::Text => "TEXT".into(),
                 LogicFieldType::Blob => "BLOB".into(),
                 LogicFieldType::Timestamptz => "INTEGER".into(),
@@ -53,12 +55,40 @@ impl LogicFieldType {
                 LogicFieldType::I32 => "INT4".into(),
                 LogicFieldType::I64 => "INT8".into(),
                 LogicFieldType::F64 => "FLOAT8".into(),
-                LogicFieldType::Varchar(len) => format!("Varchar({len})"),
+                LogicFieldType::Varchar(len, LengthUnit::Chars) => format!("Varchar({len})"),
+                // Postgres counts chars, bytes are checked by a constraint, graphemes not at all.
+                LogicFieldType::Varchar(_, LengthUnit::Bytes | LengthUnit::Graphemes) => {
+                    "TEXT".into()
+                }
                 LogicFieldType::Text => "TEXT".into(),
                 LogicFieldType::Blob => "BLOB".into(),
                 LogicFieldType::Timestamptz => "TIMESTAMPTZ".into(),
                 LogicFieldType::Timestamp => "TIMESTAMP".into(),
             },
+            // duckdb does not enforce varchar lengths
+            crate::DbType::DuckDb => match self {
+                LogicFieldType::Bool => "BOOLEAN".into(),
+                LogicFieldType::I8 => "TINYINT".into(),
+                LogicFieldType::I16 => "SMALLINT".into(),
+                LogicFieldType::I32 => "INTEGER".into(),
+                LogicFieldType::I64 => "BIGINT".into(),
+                LogicFieldType::F64 => "DOUBLE".into(),
+                LogicFieldType::Varchar(..) => "VARCHAR".into(),
+                LogicFieldType::Text => "VARCHAR".into(),
+                LogicFieldType::Blob => "BLOB".into(),
+                LogicFieldType::Timestamptz => "TIMESTAMPTZ".into(),
+                LogicFieldType::Timestamp => "TIMESTAMP".into(),
+            },
+        }
+    }
+
+    fn to_check(self, column: &str, db_type: crate::DbType) -> Option<String> {
+        match (db_type, self) {
+            (
+                crate::DbType::Postgres | crate::DbType::DuckDb,
+                LogicFieldType::Varchar(len, LengthUnit::Bytes),
+            ) => Some(format!("check (octet_length({column}) <= {len})")),
+            _ => None,
         }
     }
 }
@@ -75,7 +105,7 @@ impl CreateTableSql {
                 .map(|(k, v)| (k.to_string(), v.iter().map(|e| e.to_string()).collect()))
                 .collect(),
             keys: self
-                .unikeys
+                .keys
                 .iter()
                 .map(|(k, v)| (k.to_string(), v.iter().map(|e| e.to_string()).collect()))
                 .collect(),
@@ -94,12 +124,15 @@ impl CreateTableSqlOwned {
             .fields
             .iter()
             .map(|f| {
-                format!(
-                    "{} {} {}",
-                    f.name,
-                    f.kind.to_type(db_type),
-                    if f.not_null { "not null" } else { "" }
-                )
+                let mut column = format!("{} {}", f.name, f.kind.to_type(db_type));
+                if f.not_null {
+                    column.push_str(" not null");
+                }
+                if let Some(check) = f.kind.to_check(f.name, db_type) {
+                    column.push(' ');
+                    column.push_str(&check);
+                }
+                column
             })
             .collect();
         sr = sr.seg(columns.join(", "));
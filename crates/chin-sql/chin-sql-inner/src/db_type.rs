@@ -1,10 +1,13 @@
-#[derive(Clone, Copy)]
+use crate::str_type::LengthUnit;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DbType {
     Sqlite,
     Postgres,
+    DuckDb,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LogicFieldType {
     Bool,
     I8,
@@ -12,7 +15,7 @@ pub enum LogicFieldType {
     I32,
     I64,
     F64,
-    Varchar(u16),
+    Varchar(u16, LengthUnit),
     Text,
     Blob,
     Timestamptz,
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use actor_sqlite::{
+    ActorSqliteRow, RsValue,
+    client::{ActorSqliteConnClient, ActorSqliteTxClient},
+    hook::{QueryEvent, QueryHook},
+};
+
+use crate::{ChinSqlError, DbType, SqlSeg, SqlValueStatic};
+
+use super::{SendSqlExecutor, SqlEvent, SqlHook, SqlRows, execute_error};
+
+/// A [`SqlHook`] as the hook of the actor sqlite workers, e.g. for
+/// `PoolConfig::query_hook`. [`super::set_sql_hook`] sets it for the pools without one.
+pub struct WorkerHook(pub Arc<dyn SqlHook>);
+
+impl QueryHook for WorkerHook {
+    fn redact(&self) -> bool {
+        self.0.redact()
+    }
+
+    fn on_query(&self, event: &QueryEvent<'_>) {
+        let values: Option<Vec<SqlValueStatic>> = event
+            .params
+            .map(|params| params.iter().cloned().map(SqlValueStatic::from).collect());
+        let err;
+        let result = match event.result {
+            Ok(rows) => Ok(rows),
+            Err(e) => {
+                err = ChinSqlError::ExecuteError(e.to_string().into());
+                Err(&err)
+            }
+        };
+        self.0.on_query(&SqlEvent {
+            db_type: DbType::Sqlite,
+            sql: event.sql,
+            values: values.as_deref(),
+            elapsed: event.elapsed,
+            result,
+        });
+    }
+}
+
+fn params(seg: SqlSeg<'_>) -> Result<(String, Vec<RsValue>), ChinSqlError> {
+    let values = seg
+        .values
+        .into_iter()
+        .map(RsValue::try_from)
+        .collect::<Result<_, _>>()?;
+    Ok((seg.seg, values))
+}
+
+fn to_rows(rows: Vec<ActorSqliteRow>) -> SqlRows {
+    let columns = rows
+        .first()
+        .map(|row| row.cells.iter().map(|(name, _)| name.clone()).collect())
+        .unwrap_or_default();
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            row.cells
+                .into_iter()
+                .map(|(_, v)| SqlValueStatic::from(v))
+                .collect()
+        })
+        .collect();
+    SqlRows { columns, rows }
+}
+
+impl SendSqlExecutor for ActorSqliteConnClient {
+    fn db_type(&self) -> DbType {
+        DbType::Sqlite
+    }
+
+    // reported by the `QueryHook` of the workers, see `SqlHook`
+    fn reports_itself(&self) -> bool {
+        true
+    }
+
+    async fn execute_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<usize, ChinSqlError> {
+        let (sql, params) = params(seg)?;
+        ActorSqliteConnClient::execute(self, sql, params)
+            .await
+            .map_err(execute_error)
+    }
+
+    async fn query_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<SqlRows, ChinSqlError> {
+        let (sql, params) = params(seg)?;
+        ActorSqliteConnClient::query(self, sql, params)
+            .await
+            .map(to_rows)
+            .map_err(execute_error)
+    }
+}
+
+impl SendSqlExecutor for ActorSqliteTxClient {
+    fn db_type(&self) -> DbType {
+        DbType::Sqlite
+    }
+
+    fn reports_itself(&self) -> bool {
+        true
+    }
+
+    async fn execute_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<usize, ChinSqlError> {
+        let (sql, params) = params(seg)?;
+        ActorSqliteTxClient::execute(self, sql, params)
+            .await
+            .map_err(execute_error)
+    }
+
+    async fn query_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<SqlRows, ChinSqlError> {
+        let (sql, params) = params(seg)?;
+        ActorSqliteTxClient::query(self, sql, params)
+            .await
+            .map(to_rows)
+            .map_err(execute_error)
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use duckdb::{Connection, ToSql, types::Value};
+
+use crate::{ChinSqlError, DbType, SqlSeg, SqlValueStatic};
+
+use super::{SqlExecutor, SqlRows, execute_error};
+
+fn params<'a, 'b>(seg: &'b SqlSeg<'a>) -> Vec<&'b dyn ToSql> {
+    seg.values.iter().map(|v| v as &dyn ToSql).collect()
+}
+
+impl SqlExecutor for Connection {
+    fn db_type(&self) -> DbType {
+        DbType::DuckDb
+    }
+
+    async fn execute_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<usize, ChinSqlError> {
+        let mut stmt = self.prepare(&seg.seg).map_err(execute_error)?;
+        stmt.execute(params(&seg).as_slice()).map_err(execute_error)
+    }
+
+    async fn query_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<SqlRows, ChinSqlError> {
+        let mut stmt = self.prepare(&seg.seg).map_err(execute_error)?;
+        let mut rows = stmt.query(params(&seg).as_slice()).map_err(execute_error)?;
+        // duckdb knows the columns only after the statement ran
+        let columns: Vec<Arc<str>> = rows
+            .as_ref()
+            .map(|stmt| stmt.column_names().into_iter().map(Arc::from).collect())
+            .unwrap_or_default();
+
+        let mut result = vec![];
+        while let Some(row) = rows.next().map_err(execute_error)? {
+            let values = (0..columns.len())
+                .map(|i| {
+                    row.get::<_, Value>(i)
+                        .map_err(execute_error)
+                        .and_then(SqlValueStatic::try_from)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            result.push(values);
+        }
+
+        Ok(SqlRows {
+            columns,
+            rows: result,
+        })
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    future::Future,
+    ops::Deref,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{ChinSqlError, DbType, SqlSeg, SqlValue};
+
+use super::{SqlExecutor, SqlRows};
+
+static SQL_HOOK: RwLock<Option<Arc<dyn SqlHook>>> = RwLock::new(None);
+
+/// A statement run through a [`SqlExecutor`].
+#[derive(Debug)]
+pub struct SqlEvent<'e, 'a> {
+    pub db_type: DbType,
+    pub sql: &'e str,
+    /// `None` if the hook asked for redacted values.
+    pub values: Option<&'e [SqlValue<'a>]>,
+    pub elapsed: Duration,
+    /// Affected rows of an execute, returned rows of a query.
+    pub result: Result<usize, &'e ChinSqlError>,
+}
+
+/// Told about every statement of the executors, see [`set_sql_hook`] and [`HookedExecutor`].
+///
+/// The actor sqlite clients are reported by their workers, which also see the statements
+/// run without chin-sql. [`set_sql_hook`] sets the hook of the workers too, a pool with a
+/// hook of its own takes it as a `WorkerHook`. A [`HookedExecutor`] around such a client
+/// does not report them again.
+pub trait SqlHook: Send + Sync {
+    /// Keeps the bound values out of the events.
+    fn redact(&self) -> bool {
+        false
+    }
+
+    fn on_query(&self, event: &SqlEvent<'_, '_>);
+}
+
+/// Sets the hook of all executors which are not wrapped in a [`HookedExecutor`], and of the
+/// actor sqlite workers without a hook of their pool.
+pub fn set_sql_hook(hook: Option<Arc<dyn SqlHook>>) {
+    #[cfg(feature = "actor-sqlite")]
+    actor_sqlite::hook::set_query_hook(
+        hook.clone().map(|hook| {
+            Arc::new(super::WorkerHook(hook)) as Arc<dyn actor_sqlite::hook::QueryHook>
+        }),
+    );
+    *SQL_HOOK.write().unwrap_or_else(|e| e.into_inner()) = hook;
+}
+
+pub fn sql_hook() -> Option<Arc<dyn SqlHook>> {
+    SQL_HOOK.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Logs statements slower than the threshold as warnings.
+#[derive(Clone, Debug)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+    redact: bool,
+}
+
+impl SlowQueryLog {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            redact: false,
+        }
+    }
+
+    pub fn redact(self, redact: bool) -> Self {
+        Self { redact, ..self }
+    }
+}
+
+impl SqlHook for SlowQueryLog {
+    fn redact(&self) -> bool {
+        self.redact
+    }
+
+    fn on_query(&self, event: &SqlEvent<'_, '_>) {
+        if event.elapsed < self.threshold {
+            return;
+        }
+        match event.result {
+            Ok(rows) => log::warn!(
+                "slow {:?} query {:?} rows {rows}: {} {:?}",
+                event.db_type,
+                event.elapsed,
+                event.sql,
+                event.values
+            ),
+            Err(err) => log::warn!(
+                "slow {:?} query {:?} failed {err}: {} {:?}",
+                event.db_type,
+                event.elapsed,
+                event.sql,
+                event.values
+            ),
+        }
+    }
+}
+
+/// An executor with its own hook instead of the global one.
+pub struct HookedExecutor<E> {
+    executor: E,
+    hook: Arc<dyn SqlHook>,
+}
+
+impl<E> HookedExecutor<E> {
+    pub fn new(executor: E, hook: Arc<dyn SqlHook>) -> Self {
+        Self { executor, hook }
+    }
+
+    pub fn into_inner(self) -> E {
+        self.executor
+    }
+}
+
+impl<E> Deref for HookedExecutor<E> {
+    type Target = E;
+
+    fn deref(&self) -> &Self::Target {
+        &self.executor
+    }
+}
+
+impl<E: SqlExecutor> SqlExecutor for HookedExecutor<E> {
+    fn db_type(&self) -> DbType {
+        self.executor.db_type()
+    }
+
+    fn sql_hook(&self) -> Option<Arc<dyn SqlHook>> {
+        (!self.executor.reports_itself()).then(|| self.hook.clone())
+    }
+
+    fn reports_itself(&self) -> bool {
+        self.executor.reports_itself()
+    }
+
+    async fn execute_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<usize, ChinSqlError> {
+        self.executor.execute_seg(seg).await
+    }
+
+    async fn query_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<SqlRows, ChinSqlError> {
+        self.executor.query_seg(seg).await
+    }
+}
+
+/// What the executor consumes, the values are only kept if the hook wants them.
+struct Reported<'a> {
+    sql: String,
+    values: Option<Vec<SqlValue<'a>>>,
+}
+
+impl<'a> Reported<'a> {
+    fn of(hook: &dyn SqlHook, seg: &SqlSeg<'a>) -> Self {
+        Self {
+            sql: seg.seg.clone(),
+            values: (!hook.redact()).then(|| seg.values.clone()),
+        }
+    }
+
+    fn report<T>(
+        &self,
+        hook: &dyn SqlHook,
+        db_type: DbType,
+        start: Instant,
+        result: &Result<T, ChinSqlError>,
+        rows: fn(&T) -> usize,
+    ) {
+        hook.on_query(&SqlEvent {
+            db_type,
+            sql: &self.sql,
+            values: self.values.as_deref(),
+            elapsed: start.elapsed(),
+            result: result.as_ref().map(rows),
+        });
+    }
+}
+
+/// Runs `seg` and reports it to `hook`, `rows` counts the rows of the result.
+pub(crate) async fn hooked<'a, T, F>(
+    hook: Option<Arc<dyn SqlHook>>,
+    db_type: DbType,
+    seg: SqlSeg<'a>,
+    run: impl FnOnce(SqlSeg<'a>) -> F,
+    rows: fn(&T) -> usize,
+) -> Result<T, ChinSqlError>
+where
+    F: Future<Output = Result<T, ChinSqlError>>,
+{
+    let Some(hook) = hook else {
+        return run(seg).await;
+    };
+    let reported = Reported::of(&*hook, &seg);
+    let start = Instant::now();
+    let result = run(seg).await;
+    reported.report(&*hook, db_type, start, &result, rows);
+    result
+}
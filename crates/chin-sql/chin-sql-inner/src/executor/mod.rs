@@ -0,0 +1,535 @@
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "actor-sqlite")]
+mod actor_sqlite;
+
+#[cfg(feature = "tokio-postgres")]
+mod postgres;
+
+#[cfg(feature = "duckdb")]
+mod duckdb;
+
+mod hook;
+
+pub use hook::*;
+
+#[cfg(feature = "actor-sqlite")]
+pub use self::actor_sqlite::WorkerHook;
+
+use std::{future::Future, sync::Arc};
+
+use crate::{
+    ChinSqlError, DbType, IntoSqlSeg, SqlDeleter, SqlProjection, SqlSeg, SqlUpdater, SqlValueRow,
+    SqlValueStatic, TypedSqlReader, check_version,
+};
+
+pub(crate) fn execute_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> ChinSqlError {
+    ChinSqlError::ExecuteError(Box::new(err))
+}
+
+/// Rows returned by an executor, values are in the same order as `columns`.
+#[derive(Clone, Debug, Default)]
+pub struct SqlRows {
+    pub columns: Vec<Arc<str>>,
+    pub rows: Vec<Vec<SqlValueStatic>>,
+}
+
+impl SqlRows {
+    pub fn into_value_rows(self) -> Vec<SqlValueRow> {
+        let SqlRows { columns, rows } = self;
+        let columns: Arc<[Arc<str>]> = columns.into();
+        rows.into_iter()
+            .map(|values| SqlValueRow::new(columns.clone(), values))
+            .collect()
+    }
+}
+
+/// Something which runs the sql built by chin-sql, e.g. a sqlite connection or a
+/// postgres client. Only the two `*_seg` functions need to be implemented.
+///
+/// Inherent methods with the same name win on the concrete types, so call it as
+/// `SqlExecutor::query(&conn, ..)` there, or through a generic `E: SqlExecutor`.
+///
+/// All the provided functions report to [`SqlExecutor::sql_hook`], the `*_seg` ones don't.
+///
+/// The futures are not `Send`, they borrow the executor and the sqlite and duckdb
+/// connections are not `Sync`. Executors which are `Sync` implement [`SendSqlExecutor`]
+/// instead, e.g. to spawn the futures on a multi threaded runtime.
+pub trait SqlExecutor {
+    fn db_type(&self) -> DbType;
+
+    /// The global hook of [`set_sql_hook`] by default.
+    fn sql_hook(&self) -> Option<Arc<dyn SqlHook>> {
+        sql_hook().filter(|_| !self.reports_itself())
+    }
+
+    /// Whether something below reports the statements, e.g. the workers of the actor sqlite
+    /// clients, [`SqlExecutor::sql_hook`] is skipped then.
+    fn reports_itself(&self) -> bool {
+        false
+    }
+
+    fn execute_seg<'a>(&self, seg: SqlSeg<'a>)
+    -> impl Future<Output = Result<usize, ChinSqlError>>;
+
+    fn query_seg<'a>(&self, seg: SqlSeg<'a>)
+    -> impl Future<Output = Result<SqlRows, ChinSqlError>>;
+
+    fn execute<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> {
+        let seg = sql.into_sql_seg(self.db_type());
+        async move {
+            hooked(
+                self.sql_hook(),
+                self.db_type(),
+                seg?,
+                |seg| self.execute_seg(seg),
+                |n| *n,
+            )
+            .await
+        }
+    }
+
+    fn query<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<Vec<SqlValueRow>, ChinSqlError>> {
+        let seg = sql.into_sql_seg(self.db_type());
+        async move {
+            let rows = hooked(
+                self.sql_hook(),
+                self.db_type(),
+                seg?,
+                |seg| self.query_seg(seg),
+                |r| r.rows.len(),
+            );
+            Ok(rows.await?.into_value_rows())
+        }
+    }
+
+    /// Runs an updater built with a version check, see [`check_version`].
+    fn execute_versioned<'a>(
+        &self,
+        updater: SqlUpdater<'a>,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> {
+        let table = updater.table();
+        let affected = self.execute(updater);
+        async move { check_version(table, affected.await?) }
+    }
+
+    /// Runs the limited deleter from `build` until a batch deletes less than the limit,
+    /// returns the number of all deleted rows. Fails without a limit or with a limit of 0.
+    fn delete_in_batches<'a, F: Fn() -> SqlDeleter<'a>>(
+        &self,
+        build: F,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> {
+        async move {
+            let mut total = 0;
+            loop {
+                let deleter = build();
+                let limit = deleter.batch_limit()?;
+                let affected = self.execute(deleter).await?;
+                total += affected;
+                if affected < limit {
+                    return Ok(total);
+                }
+            }
+        }
+    }
+
+    /// Exactly one row is expected.
+    fn query_one<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<SqlValueRow, ChinSqlError>> {
+        let rows = self.query(sql);
+        async move { one(rows.await?) }
+    }
+
+    /// Zero or one row is expected.
+    fn query_opt<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<Option<SqlValueRow>, ChinSqlError>> {
+        let rows = self.query(sql);
+        async move { opt(rows.await?) }
+    }
+
+    fn query_typed<'a, P: SqlProjection<'a>, O: From<P::Output>>(
+        &self,
+        reader: TypedSqlReader<'a, P, O>,
+    ) -> impl Future<Output = Result<Vec<O>, ChinSqlError>> {
+        let decode = reader.decoder();
+        let seg = reader.into_sql_seg(self.db_type());
+        async move {
+            let rows = hooked(
+                self.sql_hook(),
+                self.db_type(),
+                seg?,
+                |seg| self.query_seg(seg),
+                |r| r.rows.len(),
+            );
+            rows.await?.rows.into_iter().map(decode).collect()
+        }
+    }
+}
+
+/// A [`SqlExecutor`] whose futures are `Send`, it comes with the [`SqlExecutor`]
+/// implementation. Call it as `SendSqlExecutor::query(&client, ..)` on the concrete types,
+/// where both traits are in scope.
+///
+/// A [`HookedExecutor`] is only a [`SqlExecutor`].
+pub trait SendSqlExecutor: Sync {
+    fn db_type(&self) -> DbType;
+
+    /// The global hook of [`set_sql_hook`] by default.
+    fn sql_hook(&self) -> Option<Arc<dyn SqlHook>> {
+        sql_hook().filter(|_| !self.reports_itself())
+    }
+
+    /// See [`SqlExecutor::reports_itself`].
+    fn reports_itself(&self) -> bool {
+        false
+    }
+
+    fn execute_seg<'a>(
+        &self,
+        seg: SqlSeg<'a>,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> + Send;
+
+    fn query_seg<'a>(
+        &self,
+        seg: SqlSeg<'a>,
+    ) -> impl Future<Output = Result<SqlRows, ChinSqlError>> + Send;
+
+    fn execute<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> + Send {
+        let seg = sql.into_sql_seg(self.db_type());
+        async move {
+            hooked(
+                self.sql_hook(),
+                self.db_type(),
+                seg?,
+                |seg| self.execute_seg(seg),
+                |n| *n,
+            )
+            .await
+        }
+    }
+
+    fn query<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<Vec<SqlValueRow>, ChinSqlError>> + Send {
+        let seg = sql.into_sql_seg(self.db_type());
+        async move {
+            let rows = hooked(
+                self.sql_hook(),
+                self.db_type(),
+                seg?,
+                |seg| self.query_seg(seg),
+                |r| r.rows.len(),
+            );
+            Ok(rows.await?.into_value_rows())
+        }
+    }
+
+    /// See [`SqlExecutor::execute_versioned`].
+    fn execute_versioned<'a>(
+        &self,
+        updater: SqlUpdater<'a>,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> + Send {
+        let table = updater.table();
+        let affected = self.execute(updater);
+        async move { check_version(table, affected.await?) }
+    }
+
+    /// See [`SqlExecutor::delete_in_batches`].
+    fn delete_in_batches<'a, F: Fn() -> SqlDeleter<'a> + Send>(
+        &self,
+        build: F,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> + Send {
+        async move {
+            let mut total = 0;
+            loop {
+                let deleter = build();
+                let limit = deleter.batch_limit()?;
+                let affected = self.execute(deleter).await?;
+                total += affected;
+                if affected < limit {
+                    return Ok(total);
+                }
+            }
+        }
+    }
+
+    /// Exactly one row is expected.
+    fn query_one<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<SqlValueRow, ChinSqlError>> + Send {
+        let rows = self.query(sql);
+        async move { one(rows.await?) }
+    }
+
+    /// Zero or one row is expected.
+    fn query_opt<'a, S: IntoSqlSeg<'a>>(
+        &self,
+        sql: S,
+    ) -> impl Future<Output = Result<Option<SqlValueRow>, ChinSqlError>> + Send {
+        let rows = self.query(sql);
+        async move { opt(rows.await?) }
+    }
+
+    fn query_typed<'a, P: SqlProjection<'a>, O: From<P::Output>>(
+        &self,
+        reader: TypedSqlReader<'a, P, O>,
+    ) -> impl Future<Output = Result<Vec<O>, ChinSqlError>> + Send {
+        let decode = reader.decoder();
+        let seg = reader.into_sql_seg(self.db_type());
+        async move {
+            let rows = hooked(
+                self.sql_hook(),
+                self.db_type(),
+                seg?,
+                |seg| self.query_seg(seg),
+                |r| r.rows.len(),
+            );
+            rows.await?.rows.into_iter().map(decode).collect()
+        }
+    }
+}
+
+impl<E: SendSqlExecutor> SqlExecutor for E {
+    fn db_type(&self) -> DbType {
+        SendSqlExecutor::db_type(self)
+    }
+
+    fn sql_hook(&self) -> Option<Arc<dyn SqlHook>> {
+        SendSqlExecutor::sql_hook(self)
+    }
+
+    fn reports_itself(&self) -> bool {
+        SendSqlExecutor::reports_itself(self)
+    }
+
+    fn execute_seg<'a>(
+        &self,
+        seg: SqlSeg<'a>,
+    ) -> impl Future<Output = Result<usize, ChinSqlError>> {
+        SendSqlExecutor::execute_seg(self, seg)
+    }
+
+    fn query_seg<'a>(
+        &self,
+        seg: SqlSeg<'a>,
+    ) -> impl Future<Output = Result<SqlRows, ChinSqlError>> {
+        SendSqlExecutor::query_seg(self, seg)
+    }
+}
+
+fn one(mut rows: Vec<SqlValueRow>) -> Result<SqlValueRow, ChinSqlError> {
+    match rows.len() {
+        1 => Ok(rows.remove(0)),
+        n => Err(ChinSqlError::UnexpectedRowCount(n)),
+    }
+}
+
+fn opt(mut rows: Vec<SqlValueRow>) -> Result<Option<SqlValueRow>, ChinSqlError> {
+    match rows.len() {
+        0 => Ok(None),
+        1 => Ok(Some(rows.remove(0))),
+        n => Err(ChinSqlError::UnexpectedRowCount(n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{
+        DbType, Froms, OrderBy, SqlDeleter, SqlEvent, SqlExecutor, SqlHook, SqlInserter, SqlReader,
+        SqlTypedField, Wheres, str_type::Text,
+    };
+
+    type Event = (DbType, String, Option<usize>, Option<usize>);
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Event>>);
+
+    impl SqlHook for Recorder {
+        fn redact(&self) -> bool {
+            true
+        }
+
+        fn on_query(&self, event: &SqlEvent<'_, '_>) {
+            self.0.lock().unwrap().push((
+                event.db_type,
+                event.sql.to_owned(),
+                event.values.map(|v| v.len()),
+                event.result.as_ref().ok().copied(),
+            ));
+        }
+    }
+
+    async fn roundtrip<E: SqlExecutor>(executor: &E) {
+        executor
+            .execute("create table note (id integer, body text)")
+            .await
+            .unwrap();
+        for (id, body) in [(1, "a"), (2, "b")] {
+            let inserter = SqlInserter::new("note").field("id", id).field("body", body);
+            assert_eq!(1, executor.execute(inserter).await.unwrap());
+        }
+
+        let id = SqlTypedField::<i64>::new("n", "id");
+        let body = SqlTypedField::<Text>::new("n", "body");
+        let froms = || Froms::Table {
+            table_name: "note",
+            alias: "n",
+        };
+        let reader = SqlReader::select((id.clone(), body.clone()), froms())
+            .wheres(id.v_gt(1))
+            .build_typed();
+        let rows = executor.query_typed(reader).await.unwrap();
+        assert_eq!(vec![(2, Text::from("b".to_owned()))], rows);
+
+        let one = SqlReader::builder(vec![body.erased()], froms())
+            .wheres(id.v_eq(1))
+            .build();
+        let row = executor.query_one(one).await.unwrap();
+        assert_eq!(1, row.len());
+        let none = SqlReader::builder(vec![body.erased()], froms())
+            .wheres(id.v_eq(3))
+            .build();
+        assert!(executor.query_opt(none).await.unwrap().is_none());
+        assert!(
+            executor
+                .query_one(SqlReader::builder(vec![body.erased()], froms()).build())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn rusqlite_executor() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        roundtrip(&conn).await;
+
+        for id in 3..8 {
+            let inserter = SqlInserter::new("note").field("id", id).field("body", "c");
+            assert_eq!(1, SqlExecutor::execute(&conn, inserter).await.unwrap());
+        }
+        let deleted = conn
+            .delete_in_batches(|| {
+                SqlDeleter::new("note")
+                    .r#where(Wheres::equal("body", "c"))
+                    .order_by([OrderBy::Asc("id".into())])
+                    .limit(2)
+            })
+            .await
+            .unwrap();
+        assert_eq!(5, deleted);
+    }
+
+    #[tokio::test]
+    async fn hooked_executor() {
+        use std::sync::Arc;
+
+        use crate::HookedExecutor;
+
+        let recorder = Arc::new(Recorder::default());
+        let conn = HookedExecutor::new(
+            rusqlite::Connection::open_in_memory().unwrap(),
+            recorder.clone(),
+        );
+        roundtrip(&conn).await;
+        assert!(conn.execute("select nope").await.is_err());
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(8, events.len());
+        assert_eq!(
+            (
+                DbType::Sqlite,
+                "insert  into note(id,body) values (?, ?)".to_owned(),
+                None,
+                Some(1)
+            ),
+            events[1]
+        );
+        assert_eq!(Some(1), events[3].3);
+        assert_eq!((DbType::Sqlite, None), (events[7].0, events[7].3));
+    }
+
+    #[cfg(feature = "duckdb")]
+    #[tokio::test]
+    async fn duckdb_executor() {
+        let conn = ::duckdb::Connection::open_in_memory().unwrap();
+        roundtrip(&conn).await;
+    }
+
+    #[cfg(feature = "actor-sqlite")]
+    #[tokio::test]
+    async fn actor_sqlite_executor() {
+        use actor_sqlite::{pool::ActorSqlitePool, pool_config::PoolConfig};
+
+        let pool = ActorSqlitePool::try_from(PoolConfig::default().path(":memory:")).unwrap();
+        let mut client = pool.get().await.unwrap();
+        roundtrip(&client).await;
+        // the workers report to their own hook
+        assert!(client.sql_hook().is_none());
+
+        let tx = client.transaction().await.unwrap();
+        assert_eq!(
+            1,
+            SqlExecutor::execute(&tx, "delete from note where id = 1")
+                .await
+                .unwrap()
+        );
+        tx.rollback().await.unwrap();
+
+        // the futures of the clients can be spawned
+        let rows = tokio::spawn(async move {
+            crate::SendSqlExecutor::query(&client, "select id from note").await
+        });
+        assert_eq!(2, rows.await.unwrap().unwrap().len());
+    }
+
+    #[cfg(feature = "actor-sqlite")]
+    #[tokio::test]
+    async fn hooked_actor_sqlite_executor() {
+        use std::sync::Arc;
+
+        use actor_sqlite::{pool::ActorSqlitePool, pool_config::PoolConfig};
+
+        use crate::{HookedExecutor, WorkerHook};
+
+        let recorder = Arc::new(Recorder::default());
+        let pool = ActorSqlitePool::try_from(
+            PoolConfig::default()
+                .path(":memory:")
+                .query_hook(Arc::new(WorkerHook(recorder.clone()))),
+        )
+        .unwrap();
+        let client = HookedExecutor::new(pool.get().await.unwrap(), recorder.clone());
+        roundtrip(&client).await;
+
+        // every statement is reported once, by the worker
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(7, events.len());
+        assert_eq!(
+            (
+                DbType::Sqlite,
+                "insert  into note(id,body) values (?, ?)".to_owned(),
+                None,
+                Some(1)
+            ),
+            events[1]
+        );
+    }
+}
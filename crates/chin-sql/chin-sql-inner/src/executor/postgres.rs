@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use postgres_types::ToSql;
+use tokio_postgres::Client;
+
+use crate::{ChinSqlError, DbType, SqlSeg, SqlValueStatic, sql_template::check_bound};
+
+use super::{SendSqlExecutor, SqlRows, execute_error};
+
+fn params<'a, 'b>(seg: &'b SqlSeg<'a>) -> Vec<&'b (dyn ToSql + Sync)> {
+    seg.values
+        .iter()
+        .map(|v| <&(dyn ToSql + Sync + Send)>::from(v) as &(dyn ToSql + Sync))
+        .collect()
+}
+
+impl SendSqlExecutor for Client {
+    fn db_type(&self) -> DbType {
+        DbType::Postgres
+    }
+
+    async fn execute_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<usize, ChinSqlError> {
+        check_bound(&seg)?;
+        let count = Client::execute(self, &seg.seg, params(&seg).as_slice())
+            .await
+            .map_err(execute_error)?;
+        Ok(count as usize)
+    }
+
+    async fn query_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<SqlRows, ChinSqlError> {
+        check_bound(&seg)?;
+        let rows = Client::query(self, &seg.seg, params(&seg).as_slice())
+            .await
+            .map_err(execute_error)?;
+        let columns = rows
+            .first()
+            .map(|row| row.columns().iter().map(|c| Arc::from(c.name())).collect())
+            .unwrap_or_default();
+        let rows = rows
+            .iter()
+            .map(|row| {
+                (0..row.len())
+                    .map(|i| row.try_get::<_, SqlValueStatic>(i))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(execute_error)?;
+
+        Ok(SqlRows { columns, rows })
+    }
+}
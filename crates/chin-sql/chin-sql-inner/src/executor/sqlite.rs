@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use rusqlite::{Connection, ToSql, types::Value};
+
+use crate::{ChinSqlError, DbType, SqlSeg, SqlValueStatic};
+
+use super::{SqlExecutor, SqlRows, execute_error};
+
+fn params<'a, 'b>(seg: &'b SqlSeg<'a>) -> Vec<&'b dyn ToSql> {
+    seg.values.iter().map(|v| v as &dyn ToSql).collect()
+}
+
+impl SqlExecutor for Connection {
+    fn db_type(&self) -> DbType {
+        DbType::Sqlite
+    }
+
+    async fn execute_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<usize, ChinSqlError> {
+        let mut stmt = self.prepare(&seg.seg).map_err(execute_error)?;
+        stmt.execute(params(&seg).as_slice()).map_err(execute_error)
+    }
+
+    async fn query_seg<'a>(&self, seg: SqlSeg<'a>) -> Result<SqlRows, ChinSqlError> {
+        let mut stmt = self.prepare(&seg.seg).map_err(execute_error)?;
+        let columns: Vec<Arc<str>> = stmt.column_names().into_iter().map(Arc::from).collect();
+        let len = columns.len();
+        let rows = stmt
+            .query_map(params(&seg).as_slice(), |row| {
+                (0..len)
+                    .map(|i| row.get::<_, Value>(i).map(SqlValueStatic::from))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(execute_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(execute_error)?;
+
+        Ok(SqlRows { columns, rows })
+    }
+}
@@ -0,0 +1,444 @@
+use std::{borrow::Cow, fmt::Display};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    CreateTableField, CreateTableSql, ILikeType, LimitOffset, LogicFieldType, OrderBy, SqlValue,
+    Wheres,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    In,
+    NotIn,
+    /// `like '%v%'`
+    Contains,
+    /// `like 'v%'`
+    StartsWith,
+    IsNull,
+    IsNotNull,
+}
+
+impl Display for FilterOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            FilterOp::Eq => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Gt => "gt",
+            FilterOp::Ge => "ge",
+            FilterOp::Lt => "lt",
+            FilterOp::Le => "le",
+            FilterOp::In => "in",
+            FilterOp::NotIn => "not_in",
+            FilterOp::Contains => "contains",
+            FilterOp::StartsWith => "starts_with",
+            FilterOp::IsNull => "is_null",
+            FilterOp::IsNotNull => "is_not_null",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FilterValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Vec<FilterValue>),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterCond {
+    pub field: String,
+    pub op: FilterOp,
+    #[serde(default)]
+    pub value: Option<FilterValue>,
+}
+
+/// `{"and": [..]}`, `{"or": [..]}`, `{"not": {..}}` or `{"field": .., "op": .., "value": ..}`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum FilterNode {
+    And { and: Vec<FilterNode> },
+    Or { or: Vec<FilterNode> },
+    Not { not: Box<FilterNode> },
+    Cond(FilterCond),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterSort {
+    pub field: String,
+    #[serde(default)]
+    pub desc: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterPage {
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// A list query as sent by clients.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterQuery {
+    pub filter: Option<FilterNode>,
+    pub sort: Vec<FilterSort>,
+    pub page: Option<FilterPage>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FilterError {
+    #[error("unknown field {0}")]
+    UnknownField(String),
+    #[error("operator {op} is not allowed on {field}")]
+    OperatorNotAllowed { field: String, op: FilterOp },
+    #[error("invalid value for {field}, expected {expected:?}")]
+    InvalidValue {
+        field: String,
+        expected: LogicFieldType,
+    },
+    #[error("limit {limit} is larger than {max}")]
+    LimitTooLarge { limit: usize, max: usize },
+}
+
+/// The columns of a table which clients may filter and sort on.
+#[derive(Clone, Debug)]
+pub struct FilterSchema {
+    table: &'static CreateTableSql,
+    table_alias: Option<&'static str>,
+    columns: Option<&'static [&'static str]>,
+    max_limit: usize,
+}
+
+/// A [`FilterQuery`] checked against a [`FilterSchema`].
+#[derive(Debug)]
+pub struct CompiledFilter {
+    pub wheres: Wheres<'static>,
+    pub order_by: Vec<OrderBy<'static>>,
+    pub limit: Option<LimitOffset>,
+}
+
+impl FilterSchema {
+    /// All columns of the table are allowed, the page size is at most 100.
+    pub fn new(table: &'static CreateTableSql) -> Self {
+        Self {
+            table,
+            table_alias: None,
+            columns: None,
+            max_limit: 100,
+        }
+    }
+
+    /// Only these columns are allowed.
+    pub fn columns(self, columns: &'static [&'static str]) -> Self {
+        Self {
+            columns: Some(columns),
+            ..self
+        }
+    }
+
+    pub fn table_alias(self, table_alias: &'static str) -> Self {
+        Self {
+            table_alias: Some(table_alias),
+            ..self
+        }
+    }
+
+    pub fn max_limit(self, max_limit: usize) -> Self {
+        Self { max_limit, ..self }
+    }
+
+    pub(crate) fn field(&self, name: &str) -> Result<&'static CreateTableField, FilterError> {
+        let allowed = self.columns.is_none_or(|cs| cs.contains(&name));
+        self.table
+            .fields
+            .iter()
+            .find(|f| allowed && f.name == name)
+            .ok_or_else(|| FilterError::UnknownField(name.to_owned()))
+    }
+
+    // only names of the schema reach the sql, never the names sent by clients
+    fn key(&self, field: &'static CreateTableField) -> Cow<'static, str> {
+        match self.table_alias {
+            Some(alias) => format!("{alias}.{}", field.name).into(),
+            None => field.name.into(),
+        }
+    }
+
+    pub fn compile(&self, query: &FilterQuery) -> Result<CompiledFilter, FilterError> {
+        let wheres = match &query.filter {
+            Some(node) => self.compile_node(node)?,
+            None => Wheres::None,
+        };
+        let order_by = query
+            .sort
+            .iter()
+            .map(|s| {
+                let key = self.key(self.field(&s.field)?);
+                Ok(if s.desc {
+                    OrderBy::Desc(key)
+                } else {
+                    OrderBy::Asc(key)
+                })
+            })
+            .collect::<Result<Vec<_>, FilterError>>()?;
+        let limit = match query.page {
+            Some(page) if page.limit > self.max_limit => {
+                return Err(FilterError::LimitTooLarge {
+                    limit: page.limit,
+                    max: self.max_limit,
+                });
+            }
+            Some(page) => Some(LimitOffset::new(page.limit).offset(page.offset)),
+            None => None,
+        };
+        Ok(CompiledFilter {
+            wheres,
+            order_by,
+            limit,
+        })
+    }
+
+    pub fn compile_node(&self, node: &FilterNode) -> Result<Wheres<'static>, FilterError> {
+        let compile_all = |nodes: &[FilterNode]| {
+            nodes
+                .iter()
+                .map(|n| self.compile_node(n))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(match node {
+            FilterNode::And { and } => Wheres::and(compile_all(and)?),
+            FilterNode::Or { or } => Wheres::or(compile_all(or)?),
+            FilterNode::Not { not } => Wheres::not(self.compile_node(not)?),
+            FilterNode::Cond(cond) => self.compile_cond(cond)?,
+        })
+    }
+
+    fn compile_cond(&self, cond: &FilterCond) -> Result<Wheres<'static>, FilterError> {
+        let field = self.field(&cond.field)?;
+        let key = self.key(field);
+        let not_allowed = || FilterError::OperatorNotAllowed {
+            field: cond.field.clone(),
+            op: cond.op,
+        };
+        let invalid = || FilterError::InvalidValue {
+            field: cond.field.clone(),
+            expected: field.kind,
+        };
+        let is_text = matches!(
+            field.kind,
+            LogicFieldType::Text | LogicFieldType::Varchar(..)
+        );
+        if matches!(field.kind, LogicFieldType::Blob) {
+            return Err(not_allowed());
+        }
+
+        let value = || cond.value.as_ref().ok_or_else(invalid);
+        let scalar = || to_sql_value(field.kind, value()?).ok_or_else(invalid);
+        let list = || match value()? {
+            FilterValue::List(vs) if !vs.is_empty() => vs
+                .iter()
+                .map(|v| to_sql_value(field.kind, v).ok_or_else(invalid))
+                .collect::<Result<Vec<_>, _>>(),
+            _ => Err(invalid()),
+        };
+        let text = || match value()? {
+            FilterValue::Str(s) if !s.is_empty() && is_text => Ok(s.as_str()),
+            _ if !is_text => Err(not_allowed()),
+            _ => Err(invalid()),
+        };
+
+        Ok(match cond.op {
+            FilterOp::Eq => Wheres::equal(key, scalar()?),
+            FilterOp::Ne => Wheres::compare(key, "<>", scalar()?),
+            FilterOp::Gt | FilterOp::Ge | FilterOp::Lt | FilterOp::Le
+                if matches!(field.kind, LogicFieldType::Bool) =>
+            {
+                return Err(not_allowed());
+            }
+            FilterOp::Gt => Wheres::compare(key, ">", scalar()?),
+            FilterOp::Ge => Wheres::compare(key, ">=", scalar()?),
+            FilterOp::Lt => Wheres::compare(key, "<", scalar()?),
+            FilterOp::Le => Wheres::compare(key, "<=", scalar()?),
+            FilterOp::In => Wheres::r#in(key, list()?),
+            FilterOp::NotIn => Wheres::not_in(key, list()?),
+            FilterOp::Contains => Wheres::like(key, text()?, ILikeType::Fuzzy),
+            FilterOp::StartsWith => Wheres::like(key, text()?, ILikeType::RightFuzzy),
+            FilterOp::IsNull | FilterOp::IsNotNull if field.not_null => {
+                return Err(not_allowed());
+            }
+            FilterOp::IsNull => Wheres::is_null(key),
+            FilterOp::IsNotNull => Wheres::is_not_null(key),
+        })
+    }
+}
+
+fn to_sql_value(kind: LogicFieldType, value: &FilterValue) -> Option<SqlValue<'static>> {
+    Some(match (kind, value) {
+        (LogicFieldType::Bool, FilterValue::Bool(v)) => SqlValue::Bool(*v),
+        (LogicFieldType::I8, FilterValue::Int(v)) => SqlValue::I8(i8::try_from(*v).ok()?),
+        (LogicFieldType::I16, FilterValue::Int(v)) => SqlValue::I16(i16::try_from(*v).ok()?),
+        (LogicFieldType::I32, FilterValue::Int(v)) => SqlValue::I32(i32::try_from(*v).ok()?),
+        (LogicFieldType::I64, FilterValue::Int(v)) => SqlValue::I64(*v),
+        (LogicFieldType::F64, FilterValue::Int(v)) => SqlValue::F64(*v as f64),
+        (LogicFieldType::F64, FilterValue::Float(v)) => SqlValue::F64(*v),
+        (LogicFieldType::Text | LogicFieldType::Varchar(..), FilterValue::Str(v)) => {
+            SqlValue::Str(Cow::Owned(v.clone()))
+        }
+        (LogicFieldType::Timestamptz, FilterValue::Str(v)) => {
+            SqlValue::FixedOffset(DateTime::<FixedOffset>::parse_from_rfc3339(v).ok()?)
+        }
+        (LogicFieldType::Timestamp, FilterValue::Str(v)) => SqlValue::Utc(
+            DateTime::<FixedOffset>::parse_from_rfc3339(v)
+                .ok()?
+                .with_timezone(&Utc),
+        ),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CreateTableField, CreateTableSql, DbType, Froms, IntoSqlSeg, LogicFieldType, SqlBuilder,
+        str_type::LengthUnit,
+    };
+
+    use super::{FilterError, FilterOp, FilterQuery, FilterSchema};
+
+    static USER: CreateTableSql = CreateTableSql {
+        table_name: "user",
+        fields: &[
+            CreateTableField {
+                name: "id",
+                kind: LogicFieldType::I64,
+                not_null: true,
+            },
+            CreateTableField {
+                name: "name",
+                kind: LogicFieldType::Varchar(32, LengthUnit::Chars),
+                not_null: true,
+            },
+            CreateTableField {
+                name: "age",
+                kind: LogicFieldType::I16,
+                not_null: false,
+            },
+            CreateTableField {
+                name: "vip",
+                kind: LogicFieldType::Bool,
+                not_null: true,
+            },
+            CreateTableField {
+                name: "secret",
+                kind: LogicFieldType::Text,
+                not_null: true,
+            },
+        ],
+        pkey: &["id"],
+        unikeys: &[],
+        keys: &[],
+        tenant: &[],
+    };
+
+    fn compile(json: &str) -> Result<String, FilterError> {
+        let schema = FilterSchema::new(&USER)
+            .columns(&["id", "name", "age", "vip"])
+            .table_alias("u");
+        let query: FilterQuery = serde_json::from_str(json).unwrap();
+        let compiled = schema.compile(&query)?;
+        let seg = SqlBuilder::new()
+            .seg("select u.id from")
+            .merge(Froms::Table {
+                table_name: "user",
+                alias: "u",
+            })
+            .r#where(compiled.wheres)
+            .order_by(compiled.order_by)
+            .transform(|sb| match compiled.limit {
+                Some(limit) => sb.limit_offset(limit),
+                None => sb,
+            })
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        Ok(seg.seg)
+    }
+
+    #[test]
+    fn compile_filter() {
+        let sql = compile(
+            r#"{
+                "filter": {"and": [
+                    {"field": "name", "op": "contains", "value": "ab"},
+                    {"or": [
+                        {"field": "age", "op": "ge", "value": 18},
+                        {"field": "vip", "op": "eq", "value": true}
+                    ]},
+                    {"not": {"field": "id", "op": "in", "value": [1, 2]}},
+                    {"field": "age", "op": "is_not_null"}
+                ]},
+                "sort": [{"field": "age", "desc": true}, {"field": "id"}],
+                "page": {"limit": 20, "offset": 40}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            "select u.id from user as u  where u.name like $1 escape '\\' and (u.age >= $2 or u.vip = $3) and  not ( u.id in ($4,$5)) and  u.age is not null  order by  u.age desc, u.id asc limit $6 offset $7 ",
+            sql
+        );
+
+        let err = |json: &str| compile(json).unwrap_err();
+        assert_eq!(
+            FilterError::UnknownField("secret".to_owned()),
+            err(r#"{"filter": {"field": "secret", "op": "eq", "value": "x"}}"#)
+        );
+        assert_eq!(
+            FilterError::UnknownField("nope".to_owned()),
+            err(r#"{"sort": [{"field": "nope"}]}"#)
+        );
+        assert!(matches!(
+            err(r#"{"filter": {"field": "age", "op": "eq", "value": "18"}}"#),
+            FilterError::InvalidValue { .. }
+        ));
+        assert!(matches!(
+            err(r#"{"filter": {"field": "age", "op": "eq", "value": 100000}}"#),
+            FilterError::InvalidValue { .. }
+        ));
+        assert_eq!(
+            FilterError::OperatorNotAllowed {
+                field: "vip".to_owned(),
+                op: FilterOp::Gt
+            },
+            err(r#"{"filter": {"field": "vip", "op": "gt", "value": true}}"#)
+        );
+        assert!(matches!(
+            err(r#"{"filter": {"field": "id", "op": "contains", "value": "1"}}"#),
+            FilterError::OperatorNotAllowed { .. }
+        ));
+        assert!(matches!(
+            err(r#"{"filter": {"field": "name", "op": "is_null"}}"#),
+            FilterError::OperatorNotAllowed { .. }
+        ));
+        assert!(matches!(
+            err(r#"{"page": {"limit": 1000}}"#),
+            FilterError::LimitTooLarge { .. }
+        ));
+    }
+}
@@ -0,0 +1,478 @@
+use thiserror::Error;
+
+use crate::{
+    FilterCond, FilterError, FilterNode, FilterOp, FilterSchema, FilterValue, LogicFieldType,
+    Wheres,
+};
+
+/// Parentheses and `not`s a filter may nest, filters come from query strings and must not
+/// overflow the stack.
+pub const MAX_FILTER_DEPTH: usize = 32;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FilterParseErrorKind {
+    #[error("unexpected end")]
+    UnexpectedEnd,
+    #[error("unexpected {0}")]
+    Unexpected(String),
+    #[error("unterminated string")]
+    UnterminatedString,
+    #[error("nested deeper than {MAX_FILTER_DEPTH}")]
+    TooDeep,
+    #[error(transparent)]
+    Filter(FilterError),
+}
+
+/// `position` is the byte offset in the filter text.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{kind} at {position}")]
+pub struct FilterParseError {
+    pub position: usize,
+    pub kind: FilterParseErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(w) => w.clone(),
+            Token::Quoted(s) => format!("{s:?}"),
+            Token::Op(op) => (*op).to_owned(),
+            Token::LParen => "(".to_owned(),
+            Token::RParen => ")".to_owned(),
+            Token::Comma => ",".to_owned(),
+        }
+    }
+}
+
+const OPS: [&str; 9] = [">=", "<=", "!=", "<>", "=", ">", "<", "~", "^"];
+
+fn unterminated(position: usize) -> FilterParseError {
+    FilterParseError {
+        position,
+        kind: FilterParseErrorKind::UnterminatedString,
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, FilterParseError> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(pos, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push((
+                    pos,
+                    match c {
+                        '(' => Token::LParen,
+                        ')' => Token::RParen,
+                        _ => Token::Comma,
+                    },
+                ));
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, e)) => s.push(e),
+                            None => return Err(unterminated(pos)),
+                        },
+                        Some((_, e)) if e == c => break,
+                        Some((_, e)) => s.push(e),
+                        None => return Err(unterminated(pos)),
+                    }
+                }
+                tokens.push((pos, Token::Quoted(s)));
+            }
+            _ => match OPS.iter().find(|op| input[pos..].starts_with(**op)) {
+                Some(op) => {
+                    for _ in 0..op.len() {
+                        chars.next();
+                    }
+                    tokens.push((pos, Token::Op(op)));
+                }
+                None => {
+                    let mut end = pos;
+                    while let Some(&(p, c)) = chars.peek() {
+                        if c.is_whitespace()
+                            || "(),\"'".contains(c)
+                            || OPS.iter().any(|op| input[p..].starts_with(op))
+                        {
+                            break;
+                        }
+                        end = p + c.len_utf8();
+                        chars.next();
+                    }
+                    tokens.push((pos, Token::Word(input[pos..end].to_owned())));
+                }
+            },
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'s> {
+    schema: &'s FilterSchema,
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    end: usize,
+    depth: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map(|(p, _)| *p)
+            .unwrap_or(self.end)
+    }
+
+    fn error(&self, position: usize, kind: FilterParseErrorKind) -> FilterParseError {
+        FilterParseError { position, kind }
+    }
+
+    fn unexpected(&self) -> FilterParseError {
+        match self.peek() {
+            Some(t) => self.error(
+                self.position(),
+                FilterParseErrorKind::Unexpected(t.describe()),
+            ),
+            None => self.error(self.end, FilterParseErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.index += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), FilterParseError> {
+        if self.peek() == Some(&token) {
+            self.index += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn or(&mut self) -> Result<Wheres<'static>, FilterParseError> {
+        let mut items = vec![self.and()?];
+        while self.keyword("or") {
+            items.push(self.and()?);
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            Wheres::or(items)
+        })
+    }
+
+    fn and(&mut self) -> Result<Wheres<'static>, FilterParseError> {
+        let mut items = vec![self.unary()?];
+        while self.keyword("and") {
+            items.push(self.unary()?);
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            Wheres::and(items)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Wheres<'static>, FilterParseError> {
+        let position = self.position();
+        if self.keyword("not") {
+            let wheres = self.nested(position, Self::unary)?;
+            return Ok(Wheres::not(wheres));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.index += 1;
+            let wheres = self.nested(position, Self::or)?;
+            self.expect(Token::RParen)?;
+            return Ok(wheres);
+        }
+        self.cond()
+    }
+
+    fn nested<F>(&mut self, position: usize, parse: F) -> Result<Wheres<'static>, FilterParseError>
+    where
+        F: FnOnce(&mut Self) -> Result<Wheres<'static>, FilterParseError>,
+    {
+        if self.depth >= MAX_FILTER_DEPTH {
+            return Err(self.error(position, FilterParseErrorKind::TooDeep));
+        }
+        self.depth += 1;
+        let wheres = parse(self);
+        self.depth -= 1;
+        wheres
+    }
+
+    fn cond(&mut self) -> Result<Wheres<'static>, FilterParseError> {
+        let position = self.position();
+        let field = match self.next() {
+            Some((_, Token::Word(w))) => w,
+            _ => {
+                self.index -= 1;
+                return Err(self.unexpected());
+            }
+        };
+        let kind = self
+            .schema
+            .field(&field)
+            .map_err(|e| self.error(position, FilterParseErrorKind::Filter(e)))?
+            .kind;
+
+        let (op, value) = if self.keyword("is") {
+            let op = if self.keyword("not") {
+                FilterOp::IsNotNull
+            } else {
+                FilterOp::IsNull
+            };
+            if !self.keyword("null") {
+                return Err(self.unexpected());
+            }
+            (op, None)
+        } else if self.keyword("in") {
+            (FilterOp::In, Some(self.list(kind)?))
+        } else if self.keyword("not") {
+            if !self.keyword("in") {
+                return Err(self.unexpected());
+            }
+            (FilterOp::NotIn, Some(self.list(kind)?))
+        } else {
+            let op = match self.peek() {
+                Some(Token::Op("=")) => FilterOp::Eq,
+                Some(Token::Op("!=" | "<>")) => FilterOp::Ne,
+                Some(Token::Op(">")) => FilterOp::Gt,
+                Some(Token::Op(">=")) => FilterOp::Ge,
+                Some(Token::Op("<")) => FilterOp::Lt,
+                Some(Token::Op("<=")) => FilterOp::Le,
+                Some(Token::Op("~")) => FilterOp::Contains,
+                Some(Token::Op("^")) => FilterOp::StartsWith,
+                _ => return Err(self.unexpected()),
+            };
+            self.index += 1;
+            (op, Some(self.value(kind)?))
+        };
+
+        self.schema
+            .compile_node(&FilterNode::Cond(FilterCond { field, op, value }))
+            .map_err(|e| self.error(position, FilterParseErrorKind::Filter(e)))
+    }
+
+    fn list(&mut self, kind: LogicFieldType) -> Result<FilterValue, FilterParseError> {
+        self.expect(Token::LParen)?;
+        let mut values = vec![self.value(kind)?];
+        while self.peek() == Some(&Token::Comma) {
+            self.index += 1;
+            values.push(self.value(kind)?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(FilterValue::List(values))
+    }
+
+    // bare words are typed by the column, quoted strings are always text
+    fn value(&mut self, kind: LogicFieldType) -> Result<FilterValue, FilterParseError> {
+        match self.next() {
+            Some((_, Token::Quoted(s))) => Ok(FilterValue::Str(s)),
+            Some((_, Token::Word(w))) => Ok(match kind {
+                LogicFieldType::Bool if w.eq_ignore_ascii_case("true") => FilterValue::Bool(true),
+                LogicFieldType::Bool if w.eq_ignore_ascii_case("false") => FilterValue::Bool(false),
+                LogicFieldType::I8
+                | LogicFieldType::I16
+                | LogicFieldType::I32
+                | LogicFieldType::I64 => w
+                    .parse()
+                    .map(FilterValue::Int)
+                    .unwrap_or(FilterValue::Str(w)),
+                LogicFieldType::F64 => w
+                    .parse()
+                    .map(FilterValue::Float)
+                    .unwrap_or(FilterValue::Str(w)),
+                _ => FilterValue::Str(w),
+            }),
+            _ => {
+                self.index -= 1;
+                Err(self.unexpected())
+            }
+        }
+    }
+}
+
+impl FilterSchema {
+    /// Parses filters like `name~foo and (age>=18 or vip=true) and tag in (a,b)`.
+    ///
+    /// Operators are `= != <> > >= < <=`, `~` for contains, `^` for starts with,
+    /// `in (..)`, `not in (..)`, `is null` and `is not null`, joined by `and`, `or` and
+    /// `not`. Values are bound, quote them to keep spaces or operator characters.
+    pub fn parse(&self, input: &str) -> Result<Wheres<'static>, FilterParseError> {
+        let mut parser = Parser {
+            schema: self,
+            tokens: tokenize(input)?,
+            index: 0,
+            end: input.len(),
+            depth: 0,
+        };
+        if parser.peek().is_none() {
+            return Ok(Wheres::None);
+        }
+        let wheres = parser.or()?;
+        match parser.peek() {
+            Some(_) => Err(parser.unexpected()),
+            None => Ok(wheres),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CreateTableField, CreateTableSql, DbType, FilterError, FilterSchema, IntoSqlSeg,
+        LogicFieldType, SqlBuilder, SqlValue,
+    };
+
+    use super::{FilterParseError, FilterParseErrorKind, MAX_FILTER_DEPTH};
+
+    static ITEM: CreateTableSql = CreateTableSql {
+        table_name: "item",
+        fields: &[
+            CreateTableField {
+                name: "name",
+                kind: LogicFieldType::Text,
+                not_null: true,
+            },
+            CreateTableField {
+                name: "age",
+                kind: LogicFieldType::I32,
+                not_null: false,
+            },
+            CreateTableField {
+                name: "vip",
+                kind: LogicFieldType::Bool,
+                not_null: true,
+            },
+            CreateTableField {
+                name: "tag",
+                kind: LogicFieldType::Text,
+                not_null: true,
+            },
+        ],
+        pkey: &[],
+        unikeys: &[],
+        keys: &[],
+        tenant: &[],
+    };
+
+    #[test]
+    fn parse_filter() {
+        let schema = FilterSchema::new(&ITEM);
+        let wheres = schema
+            .parse("name~foo and (age>=18 or vip=true) and tag in (a,'b c') and not age is null")
+            .unwrap();
+        let seg = SqlBuilder::new()
+            .seg("select name from item")
+            .r#where(wheres)
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            "select name from item  where name like $1 escape '\\' and (age >= $2 or vip = $3) and tag in ($4,$5) and  not (  age is null )",
+            seg.seg.trim_end()
+        );
+        assert!(matches!(&seg.values[0], SqlValue::Str(s) if s == "%foo%"));
+        assert!(matches!(seg.values[1], SqlValue::I32(18)));
+        assert!(matches!(seg.values[2], SqlValue::Bool(true)));
+        assert!(matches!(&seg.values[4], SqlValue::Str(s) if s == "b c"));
+
+        // nothing of the input reaches the sql text
+        let seg = SqlBuilder::new()
+            .r#where(schema.parse("name = \"x'; drop table item; --\"").unwrap())
+            .into_sql_seg(DbType::Sqlite)
+            .unwrap();
+        assert_eq!("where name = ?", seg.seg.trim());
+
+        assert!(matches!(schema.parse("   ").unwrap(), crate::Wheres::None));
+
+        let err = |input: &str| schema.parse(input).unwrap_err();
+        assert_eq!(
+            FilterParseError {
+                position: 9,
+                kind: FilterParseErrorKind::Filter(FilterError::UnknownField("secret".to_owned()))
+            },
+            err("age=1 or secret=2")
+        );
+        assert!(matches!(
+            err("age=abc"),
+            FilterParseError {
+                position: 0,
+                kind: FilterParseErrorKind::Filter(FilterError::InvalidValue { .. })
+            }
+        ));
+        assert_eq!(
+            FilterParseError {
+                position: 13,
+                kind: FilterParseErrorKind::UnexpectedEnd
+            },
+            err("(age=1 or vip")
+        );
+        assert_eq!(
+            FilterParseError {
+                position: 6,
+                kind: FilterParseErrorKind::Unexpected(")".to_owned())
+            },
+            err("age=1 )")
+        );
+        assert_eq!(
+            FilterParseError {
+                position: 5,
+                kind: FilterParseErrorKind::UnterminatedString
+            },
+            err("name=\"abc")
+        );
+        assert_eq!(
+            FilterParseError {
+                position: 32,
+                kind: FilterParseErrorKind::TooDeep
+            },
+            err(&format!("{}age=1", "(".repeat(10_000)))
+        );
+        assert_eq!(
+            FilterParseError {
+                position: 128,
+                kind: FilterParseErrorKind::TooDeep
+            },
+            err(&format!("{}age=1", "not ".repeat(10_000)))
+        );
+        let nested = |n| format!("{}age=1{}", "(".repeat(n), ")".repeat(n));
+        assert!(schema.parse(&nested(MAX_FILTER_DEPTH)).is_ok());
+        assert!(schema.parse(&nested(MAX_FILTER_DEPTH + 1)).is_err());
+    }
+}
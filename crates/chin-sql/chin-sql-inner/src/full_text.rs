@@ -0,0 +1,459 @@
+use std::borrow::Cow;
+
+use crate::{
+    ChinSqlError, CreateTableSql, CustomSqlSeg, DbType, LogicFieldType, PlaceHolderType, SqlSeg,
+    SqlValue, Wheres,
+};
+
+/// Full text index over text columns of a table.
+///
+/// On sqlite it is a FTS5 virtual table `{table}_{name}` in external content mode, kept in
+/// sync by triggers, so the base table must have a rowid. On postgres it is a generated
+/// `tsvector` column `{name}` with a GIN index. On duckdb it is the index of the `fts`
+/// extension keyed by the single column primary key, it is not kept in sync, run
+/// [`FullTextIndex::rebuild_sql`] after writes.
+#[derive(Clone, Debug)]
+pub struct FullTextIndex {
+    pub table: &'static CreateTableSql,
+    pub name: &'static str,
+    pub columns: &'static [&'static str],
+    /// text search config of postgres
+    pub language: &'static str,
+}
+
+impl FullTextIndex {
+    pub fn new(
+        table: &'static CreateTableSql,
+        name: &'static str,
+        columns: &'static [&'static str],
+    ) -> Self {
+        Self {
+            table,
+            name,
+            columns,
+            language: "simple",
+        }
+    }
+
+    pub fn language(self, language: &'static str) -> Self {
+        Self { language, ..self }
+    }
+
+    pub fn fts_table(&self) -> String {
+        format!("{}_{}", self.table.table_name, self.name)
+    }
+
+    fn check(&self) -> Result<(), ChinSqlError> {
+        if self.columns.is_empty() {
+            return Err(ChinSqlError::BuilderSqlError(format!(
+                "full text index {} has no column",
+                self.name
+            )));
+        }
+        for column in self.columns {
+            match self.table.fields.iter().find(|f| f.name == *column) {
+                Some(f) if matches!(f.kind, LogicFieldType::Text | LogicFieldType::Varchar(..)) => {
+                }
+                _ => {
+                    return Err(ChinSqlError::BuilderSqlError(format!(
+                        "{} is not a text column of {}",
+                        column, self.table.table_name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn sqls(&self, db_type: DbType) -> Result<Vec<String>, ChinSqlError> {
+        self.check()?;
+        let table = self.table.table_name;
+        let columns = self.columns.join(", ");
+        let with_prefix = |prefix: &str| {
+            self.columns
+                .iter()
+                .map(|c| format!("{prefix}.{c}"))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        let sqls = match db_type {
+            DbType::Sqlite => {
+                let fts = self.fts_table();
+                let insert = format!(
+                    "insert into {fts}(rowid, {columns}) values (new.rowid, {});",
+                    with_prefix("new")
+                );
+                let delete = format!(
+                    "insert into {fts}({fts}, rowid, {columns}) values ('delete', old.rowid, {});",
+                    with_prefix("old")
+                );
+                vec![
+                    format!(
+                        "create virtual table if not exists {fts} using fts5({columns}, content='{table}', content_rowid='rowid')"
+                    ),
+                    format!(
+                        "create trigger if not exists {fts}_ai after insert on {table} begin {insert} end"
+                    ),
+                    format!(
+                        "create trigger if not exists {fts}_ad after delete on {table} begin {delete} end"
+                    ),
+                    format!(
+                        "create trigger if not exists {fts}_au after update on {table} begin {delete} {insert} end"
+                    ),
+                ]
+            }
+            DbType::DuckDb => vec![
+                "install fts".to_owned(),
+                "load fts".to_owned(),
+                self.duckdb_pragma()?,
+            ],
+            DbType::Postgres => {
+                let document = self
+                    .columns
+                    .iter()
+                    .map(|c| format!("coalesce({c}, '')"))
+                    .collect::<Vec<String>>()
+                    .join(" || ' ' || ");
+                vec![
+                    format!(
+                        "alter table {table} add column if not exists {} tsvector generated always as (to_tsvector('{}', {document})) stored",
+                        self.name, self.language
+                    ),
+                    format!(
+                        "create index if not exists {table}_{} on {table} using gin ({})",
+                        self.name, self.name
+                    ),
+                ]
+            }
+        };
+        Ok(sqls)
+    }
+
+    /// Fills the sqlite index with rows inserted before it was created, on duckdb the index
+    /// is created again.
+    pub fn rebuild_sql(&self, db_type: DbType) -> Option<String> {
+        match db_type {
+            DbType::Sqlite => {
+                let fts = self.fts_table();
+                Some(format!("insert into {fts}({fts}) values ('rebuild')"))
+            }
+            DbType::Postgres => None,
+            DbType::DuckDb => self.duckdb_pragma().ok(),
+        }
+    }
+
+    fn duckdb_key(&self) -> Result<&'static str, ChinSqlError> {
+        match self.table.pkey {
+            [key] => Ok(*key),
+            _ => Err(ChinSqlError::BuilderSqlError(format!(
+                "full text index {} on duckdb needs a single column primary key",
+                self.name
+            ))),
+        }
+    }
+
+    fn duckdb_pragma(&self) -> Result<String, ChinSqlError> {
+        let columns: Vec<String> = self.columns.iter().map(|c| format!("'{c}'")).collect();
+        Ok(format!(
+            "pragma create_fts_index('{}', '{}', {}, overwrite = 1)",
+            self.table.table_name,
+            self.duckdb_key()?,
+            columns.join(", ")
+        ))
+    }
+
+    fn expr<S: Into<String>>(
+        &self,
+        table_alias: &str,
+        query: S,
+        kind: FullTextKind,
+    ) -> FullTextExpr {
+        FullTextExpr {
+            index: self.clone(),
+            table_alias: table_alias.to_owned(),
+            query: query.into(),
+            kind,
+        }
+    }
+
+    /// The query is in FTS5 syntax on sqlite, `websearch_to_tsquery` syntax on postgres and
+    /// plain terms on duckdb.
+    pub fn matches<'a, S: Into<String>>(&self, table_alias: &str, query: S) -> Wheres<'a> {
+        Wheres::FullText(self.expr(table_alias, query, FullTextKind::Match))
+    }
+
+    /// Higher is better on all databases.
+    pub fn rank<S: Into<String>>(&self, table_alias: &str, query: S) -> FullTextExpr {
+        self.expr(table_alias, query, FullTextKind::Rank)
+    }
+
+    /// Fragment of `column` with the matched terms wrapped in `<b>` and `</b>`, the whole
+    /// column on duckdb.
+    pub fn snippet<S: Into<String>>(
+        &self,
+        table_alias: &str,
+        column: &'static str,
+        query: S,
+    ) -> Result<FullTextExpr, ChinSqlError> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| *c == column)
+            .ok_or_else(|| {
+                ChinSqlError::BuilderSqlError(format!("{column} is not in {}", self.name))
+            })?;
+        Ok(self.expr(table_alias, query, FullTextKind::Snippet(column, index)))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum FullTextKind {
+    Match,
+    Rank,
+    Snippet(&'static str, usize),
+}
+
+/// A full text predicate or select expression, rendered per [`DbType`].
+#[derive(Clone, Debug)]
+pub struct FullTextExpr {
+    index: FullTextIndex,
+    table_alias: String,
+    query: String,
+    kind: FullTextKind,
+}
+
+impl FullTextExpr {
+    pub(crate) fn to_sql_seg<'a>(
+        &self,
+        db_type: DbType,
+        pht: &mut PlaceHolderType,
+    ) -> Result<SqlSeg<'a>, ChinSqlError> {
+        let alias = &self.table_alias;
+        if let (DbType::DuckDb, FullTextKind::Snippet(column, _)) = (db_type, &self.kind) {
+            // the fts extension of duckdb has no snippets
+            return Ok(SqlSeg::of(format!("{alias}.{column}"), vec![]));
+        }
+        let ph = pht.next_ph();
+        let seg = match db_type {
+            DbType::Sqlite => {
+                let fts = self.index.fts_table();
+                let matched = format!("from {fts} where {fts} match {ph}");
+                match self.kind {
+                    FullTextKind::Match => format!("{alias}.rowid in (select rowid {matched})"),
+                    FullTextKind::Rank => {
+                        format!("(select -bm25({fts}) {matched} and rowid = {alias}.rowid)")
+                    }
+                    FullTextKind::Snippet(_, index) => format!(
+                        "(select snippet({fts}, {index}, '<b>', '</b>', '...', 16) {matched} and rowid = {alias}.rowid)"
+                    ),
+                }
+            }
+            DbType::Postgres => {
+                let language = self.index.language;
+                let query = format!("websearch_to_tsquery('{language}', {ph})");
+                let tsv = self.index.name;
+                match self.kind {
+                    FullTextKind::Match => format!("{alias}.{tsv} @@ {query}"),
+                    FullTextKind::Rank => format!("ts_rank({alias}.{tsv}, {query})"),
+                    FullTextKind::Snippet(column, _) => {
+                        format!("ts_headline('{language}', {alias}.{column}, {query})")
+                    }
+                }
+            }
+            DbType::DuckDb => {
+                let key = self.index.duckdb_key()?;
+                let score = format!(
+                    "fts_main_{}.match_bm25({alias}.{key}, {ph})",
+                    self.index.table.table_name
+                );
+                match self.kind {
+                    FullTextKind::Match => format!("{score} is not null"),
+                    FullTextKind::Rank | FullTextKind::Snippet(..) => score,
+                }
+            }
+        };
+        Ok(SqlSeg::of(
+            seg,
+            vec![SqlValue::Str(Cow::Owned(self.query.clone()))],
+        ))
+    }
+}
+
+impl<'a> CustomSqlSeg<'a> for FullTextExpr {
+    fn build(
+        &self,
+        db_type: DbType,
+        value_type: &mut PlaceHolderType,
+    ) -> Result<Option<SqlSeg<'a>>, ChinSqlError> {
+        self.to_sql_seg(db_type, value_type).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        CreateTableField, CreateTableSql, CustomSqlSeg, DbType, IntoSqlSeg, LogicFieldType,
+        PlaceHolderType, SqlBuilder, str_type::LengthUnit,
+    };
+
+    use super::FullTextIndex;
+
+    static NOTE: CreateTableSql = CreateTableSql {
+        table_name: "note",
+        fields: &[
+            CreateTableField {
+                name: "id",
+                kind: LogicFieldType::I64,
+                not_null: true,
+            },
+            CreateTableField {
+                name: "title",
+                kind: LogicFieldType::Varchar(64, LengthUnit::Chars),
+                not_null: true,
+            },
+            CreateTableField {
+                name: "body",
+                kind: LogicFieldType::Text,
+                not_null: false,
+            },
+        ],
+        pkey: &["id"],
+        unikeys: &[],
+        keys: &[],
+        tenant: &[],
+    };
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_fts5() {
+        let index = FullTextIndex::new(&NOTE, "search", &["title", "body"]);
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        for sql in NOTE.to_owned_sql().sqls(DbType::Sqlite).unwrap() {
+            conn.execute(&sql, []).unwrap();
+        }
+        conn.execute(
+            "insert into note values (1, 'rust', 'full text search')",
+            [],
+        )
+        .unwrap();
+        for sql in index.sqls(DbType::Sqlite).unwrap() {
+            conn.execute(&sql, []).unwrap();
+        }
+        conn.execute(&index.rebuild_sql(DbType::Sqlite).unwrap(), [])
+            .unwrap();
+        conn.execute("insert into note values (2, 'sqlite', 'virtual table')", [])
+            .unwrap();
+        conn.execute("update note set body = 'text again' where id = 2", [])
+            .unwrap();
+
+        let seg = SqlBuilder::new()
+            .seg("select n.id,")
+            .custom(index.snippet("n", "body", "text").unwrap())
+            .seg("from note as n")
+            .r#where(index.matches("n", "text"))
+            .seg("order by")
+            .custom(index.rank("n", "text"))
+            .seg("desc")
+            .into_sql_seg(DbType::Sqlite)
+            .unwrap();
+        let params: Vec<String> = seg
+            .values
+            .iter()
+            .map(|v| match v {
+                crate::SqlValue::Str(s) => s.to_string(),
+                _ => unreachable!(),
+            })
+            .collect();
+        let mut stmt = conn.prepare(&seg.seg).unwrap();
+        let rows: Vec<(i64, String)> = stmt
+            .query_map(rusqlite::params_from_iter(params), |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(2, rows.len());
+        assert!(
+            rows.iter()
+                .any(|(id, s)| *id == 2 && s == "<b>text</b> again")
+        );
+
+        assert!(
+            FullTextIndex::new(&NOTE, "bad", &["id"])
+                .sqls(DbType::Sqlite)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn postgres_tsvector() {
+        let index = FullTextIndex::new(&NOTE, "search", &["title", "body"]).language("english");
+        let sqls = index.sqls(DbType::Postgres).unwrap();
+        assert_eq!(
+            "alter table note add column if not exists search tsvector generated always as (to_tsvector('english', coalesce(title, '') || ' ' || coalesce(body, ''))) stored",
+            sqls[0]
+        );
+        assert_eq!(
+            "create index if not exists note_search on note using gin (search)",
+            sqls[1]
+        );
+
+        let seg = SqlBuilder::new()
+            .seg("select")
+            .custom(index.rank("n", "rust"))
+            .seg("from note as n")
+            .r#where(index.matches("n", "rust"))
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            "select ts_rank(n.search, websearch_to_tsquery('english', $1)) from note as n  where n.search @@ websearch_to_tsquery('english', $2) ",
+            seg.seg
+        );
+    }
+
+    #[test]
+    fn duckdb_fts() {
+        let index = FullTextIndex::new(&NOTE, "search", &["title", "body"]);
+        assert_eq!(
+            "pragma create_fts_index('note', 'id', 'title', 'body', overwrite = 1)",
+            index.sqls(DbType::DuckDb).unwrap()[2]
+        );
+
+        let seg = SqlBuilder::new()
+            .seg("select")
+            .custom(index.snippet("n", "body", "rust").unwrap())
+            .seg(",")
+            .custom(index.rank("n", "rust"))
+            .seg("from note as n")
+            .r#where(index.matches("n", "rust"))
+            .into_sql_seg(DbType::DuckDb)
+            .unwrap();
+        assert_eq!(
+            "select n.body , fts_main_note.match_bm25(n.id, $1) from note as n  where fts_main_note.match_bm25(n.id, $2) is not null ",
+            seg.seg
+        );
+        assert_eq!(2, seg.values.len());
+
+        static LOG: CreateTableSql = CreateTableSql { pkey: &[], ..NOTE };
+        let index = FullTextIndex::new(&LOG, "search", &["title"]);
+        for custom in [false, true] {
+            let sb = SqlBuilder::new().seg("select");
+            let sb = if custom {
+                sb.custom(index.rank("n", "rust")).seg("from log as n")
+            } else {
+                sb.seg("* from log as n")
+                    .r#where(index.matches("n", "rust"))
+            };
+            assert!(sb.into_sql_seg(DbType::DuckDb).is_err());
+        }
+        let mut pht = PlaceHolderType::dollar_number();
+        assert!(
+            index
+                .matches("n", "rust")
+                .build(DbType::DuckDb, &mut pht)
+                .is_err()
+        );
+        assert!(CustomSqlSeg::build(&index.rank("n", "rust"), DbType::DuckDb, &mut pht).is_err());
+    }
+}
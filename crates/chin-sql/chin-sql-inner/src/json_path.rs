@@ -0,0 +1,374 @@
+use std::{borrow::Cow, fmt::Write};
+
+use crate::{ChinSqlError, DbType, LogicFieldType, OrderBy, SqlTypedField, SqlValue, Wheres};
+
+#[derive(Clone, Debug)]
+pub enum JsonKey<'a> {
+    Key(Cow<'a, str>),
+    Index(usize),
+}
+
+/// A path into a json document stored in a text column.
+///
+/// Rendered as `json_extract(col, '$.a.b')` on sqlite, `col::jsonb->'a'->>'b'` on
+/// postgres and `json_extract_string(col, '$.a.b')` on duckdb, where the extracted value
+/// is text unless it is compared with a value or [`JsonPath::cast`] is set.
+#[derive(Clone, Debug)]
+pub struct JsonPath<'a> {
+    column: Cow<'a, str>,
+    keys: Vec<JsonKey<'a>>,
+    cast: Option<LogicFieldType>,
+}
+
+fn quote_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn cast_type(kind: LogicFieldType) -> &'static str {
+    match kind {
+        LogicFieldType::Bool => "BOOL",
+        LogicFieldType::I8 | LogicFieldType::I16 => "INT2",
+        LogicFieldType::I32 => "INT4",
+        LogicFieldType::I64 => "INT8",
+        LogicFieldType::F64 => "FLOAT8",
+        LogicFieldType::Varchar(..) | LogicFieldType::Text => "TEXT",
+        LogicFieldType::Blob => "BYTEA",
+        LogicFieldType::Timestamptz => "TIMESTAMPTZ",
+        LogicFieldType::Timestamp => "TIMESTAMP",
+    }
+}
+
+fn value_type(value: &SqlValue) -> Option<LogicFieldType> {
+    match value {
+        SqlValue::Bool(_) => Some(LogicFieldType::Bool),
+        SqlValue::I8(_) => Some(LogicFieldType::I8),
+        SqlValue::I16(_) => Some(LogicFieldType::I16),
+        SqlValue::I32(_) => Some(LogicFieldType::I32),
+        SqlValue::I64(_) => Some(LogicFieldType::I64),
+        SqlValue::F64(_) => Some(LogicFieldType::F64),
+        SqlValue::FixedOffset(_) => Some(LogicFieldType::Timestamptz),
+        SqlValue::Utc(_) => Some(LogicFieldType::Timestamp),
+        SqlValue::Null(kind) => Some(*kind),
+        SqlValue::Str(_) | SqlValue::Blob(_) | SqlValue::NullUnknown | SqlValue::Slot(_) => None,
+    }
+}
+
+impl<'a> JsonPath<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(column: S) -> Self {
+        Self {
+            column: column.into(),
+            keys: vec![],
+            cast: None,
+        }
+    }
+
+    pub fn key<S: Into<Cow<'a, str>>>(mut self, key: S) -> Self {
+        self.keys.push(JsonKey::Key(key.into()));
+        self
+    }
+
+    pub fn index(mut self, index: usize) -> Self {
+        self.keys.push(JsonKey::Index(index));
+        self
+    }
+
+    /// Postgres and duckdb only, sqlite keeps the json type of the value.
+    pub fn cast(mut self, kind: LogicFieldType) -> Self {
+        self.cast = Some(kind);
+        self
+    }
+
+    fn sqlite_path(&self) -> String {
+        let mut path = String::from("$");
+        for key in &self.keys {
+            match key {
+                JsonKey::Key(k) if k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                    let _ = write!(path, ".{k}");
+                }
+                JsonKey::Key(k) => {
+                    let k = k.replace('\\', "\\\\").replace('"', "\\\"");
+                    let _ = write!(path, ".\"{k}\"");
+                }
+                JsonKey::Index(i) => {
+                    let _ = write!(path, "[{i}]");
+                }
+            }
+        }
+        quote_literal(&path)
+    }
+
+    fn postgres_path(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| match key {
+                JsonKey::Key(k) => format!("\"{}\"", k.replace('\\', "\\\\").replace('"', "\\\"")),
+                JsonKey::Index(i) => i.to_string(),
+            })
+            .collect();
+        quote_literal(&format!("{{{}}}", keys.join(",")))
+    }
+
+    pub fn to_sql(&self, db_type: DbType) -> String {
+        self.render(db_type, self.cast)
+    }
+
+    fn render(&self, db_type: DbType, cast: Option<LogicFieldType>) -> String {
+        let expr = match db_type {
+            DbType::Sqlite => {
+                return format!("json_extract({}, {})", self.column, self.sqlite_path());
+            }
+            DbType::DuckDb => format!(
+                "json_extract_string({}, {})",
+                self.column,
+                self.sqlite_path()
+            ),
+            DbType::Postgres => {
+                let mut expr = format!("{}::jsonb", self.column);
+                let len = self.keys.len();
+                for (n, key) in self.keys.iter().enumerate() {
+                    expr.push_str(if n + 1 == len { "->>" } else { "->" });
+                    match key {
+                        JsonKey::Key(k) => expr.push_str(&quote_literal(k)),
+                        JsonKey::Index(i) => expr.push_str(&i.to_string()),
+                    }
+                }
+                expr
+            }
+        };
+        match cast {
+            Some(LogicFieldType::Text | LogicFieldType::Varchar(..)) | None => expr,
+            Some(kind) => format!("({expr})::{}", cast_type(kind)),
+        }
+    }
+
+    /// The value is bound, on postgres the extracted text is cast to the type of the value.
+    pub fn compare<S: Into<Cow<'a, str>>, T: Into<SqlValue<'a>>>(
+        self,
+        operator: S,
+        value: T,
+    ) -> Wheres<'a> {
+        Wheres::Json {
+            path: self,
+            operator: operator.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn equal<T: Into<SqlValue<'a>>>(self, value: T) -> Wheres<'a> {
+        self.compare("=", value)
+    }
+
+    pub(crate) fn compare_sql(&self, db_type: DbType, value: &SqlValue) -> String {
+        self.render(db_type, self.cast.or_else(|| value_type(value)))
+    }
+
+    /// Rendered for the database the query is built for, like [`JsonPath::compare`].
+    pub fn asc(&self) -> OrderBy<'a> {
+        OrderBy::Json {
+            path: self.clone(),
+            desc: false,
+            nulls: None,
+        }
+    }
+
+    pub fn desc(&self) -> OrderBy<'a> {
+        OrderBy::Json {
+            path: self.clone(),
+            desc: true,
+            nulls: None,
+        }
+    }
+
+    /// A select field named `alias`, in the table `table_alias` for sub queries.
+    pub fn select<T>(self, table_alias: &'a str, alias: &'a str) -> SqlTypedField<'a, T> {
+        let mut field = SqlTypedField::new(table_alias, alias);
+        field.json = Some(self);
+        field
+    }
+
+    /// `json_set` on sqlite and `jsonb_set` on postgres, the value is bound as json text.
+    pub(crate) fn set_sql(&self, db_type: DbType, ph: &str) -> Result<String, ChinSqlError> {
+        Ok(match db_type {
+            DbType::Sqlite => format!(
+                "json_set({}, {}, json({ph}))",
+                self.column,
+                self.sqlite_path()
+            ),
+            DbType::Postgres => format!(
+                "jsonb_set({}::jsonb, {}, {ph}::jsonb)::text",
+                self.column,
+                self.postgres_path()
+            ),
+            DbType::DuckDb => {
+                return Err(ChinSqlError::BuilderSqlError(
+                    "duckdb can not set a json path".to_owned(),
+                ));
+            }
+        })
+    }
+
+    pub(crate) fn column(&self) -> &str {
+        &self.column
+    }
+}
+
+fn push_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Json text of a value, for `json_set` and `jsonb_set`.
+pub(crate) fn to_json_text(value: &SqlValue) -> Result<String, ChinSqlError> {
+    let mut out = String::new();
+    match value {
+        SqlValue::Bool(v) => out.push_str(if *v { "true" } else { "false" }),
+        SqlValue::I8(v) => out.push_str(&v.to_string()),
+        SqlValue::I16(v) => out.push_str(&v.to_string()),
+        SqlValue::I32(v) => out.push_str(&v.to_string()),
+        SqlValue::I64(v) => out.push_str(&v.to_string()),
+        SqlValue::F64(v) if v.is_finite() => out.push_str(&v.to_string()),
+        SqlValue::F64(v) => {
+            return Err(ChinSqlError::TransformError(format!(
+                "{v} is not valid json"
+            )));
+        }
+        SqlValue::Str(s) => push_json_str(&mut out, s),
+        SqlValue::FixedOffset(v) => push_json_str(&mut out, &v.to_rfc3339()),
+        SqlValue::Utc(v) => push_json_str(&mut out, &v.to_rfc3339()),
+        SqlValue::Blob(_) => {
+            return Err(ChinSqlError::TransformError(
+                "blob can not be set into json".to_owned(),
+            ));
+        }
+        SqlValue::Null(_) | SqlValue::NullUnknown => out.push_str("null"),
+        SqlValue::Slot(name) => {
+            return Err(ChinSqlError::TransformError(format!(
+                "slot {name} can not be set into json"
+            )));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DbType, Froms, IntoSqlSeg, JsonPath, SqlReader, SqlTypedField, SqlUpdater, Wheres,
+        str_type::Text,
+    };
+
+    #[test]
+    fn json_path() {
+        let data = SqlTypedField::<Text>::new("t", "data");
+        let city = data.json().key("addr").key("city");
+        assert_eq!(
+            "json_extract(t.data, '$.addr.city')",
+            city.to_sql(DbType::Sqlite)
+        );
+        assert_eq!(
+            "t.data::jsonb->'addr'->>'city'",
+            city.to_sql(DbType::Postgres)
+        );
+        assert_eq!(
+            r#"json_extract(t.data, '$."a\"b\\c"')"#,
+            data.json().key("a\"b\\c").to_sql(DbType::Sqlite)
+        );
+
+        let reader = || {
+            SqlReader::builder(
+                vec![
+                    data.json()
+                        .key("tags")
+                        .index(0)
+                        .select::<Text>("t", "tag")
+                        .erased(),
+                ],
+                Froms::Table {
+                    table_name: "doc",
+                    alias: "t",
+                },
+            )
+            .wheres(Wheres::and([
+                data.json().key("age").compare(">", 18),
+                data.json().key("name").equal("o'neil"),
+            ]))
+            .order_by([data.json().key("name").desc().nulls_last()])
+            .build()
+        };
+        assert_eq!(
+            "select t.data::jsonb->'tags'->>0 as tag from doc as t  where (t.data::jsonb->>'age')::INT4 > $1 and t.data::jsonb->>'name' = $2 order by t.data::jsonb->>'name' desc nulls last ",
+            reader().into_sql_seg(DbType::Postgres).unwrap().seg
+        );
+        assert_eq!(
+            "select json_extract_string(t.data, '$.tags[0]') as tag from doc as t  where (json_extract_string(t.data, '$.age'))::INT4 > $1 and json_extract_string(t.data, '$.name') = $2 order by json_extract_string(t.data, '$.name') desc nulls last ",
+            reader().into_sql_seg(DbType::DuckDb).unwrap().seg
+        );
+
+        let updater = || {
+            SqlUpdater::new("doc")
+                .json_set(JsonPath::new("data").key("a b"), "x\"y")
+                .r#where(Wheres::equal("id", 1))
+        };
+        let seg = updater().into_sql_seg(DbType::Postgres).unwrap();
+        assert_eq!(
+            " update doc set  data = jsonb_set(data::jsonb, '{\"a b\"}', $1::jsonb)::text  where id = $2",
+            seg.seg
+        );
+
+        #[cfg(feature = "sqlite")]
+        {
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            conn.execute("create table doc (id integer, data text)", [])
+                .unwrap();
+            conn.execute(
+                r#"insert into doc values (1, '{"age": 20, "name": "o''neil", "tags": ["x"]}')"#,
+                [],
+            )
+            .unwrap();
+            let seg = SqlUpdater::new("doc")
+                .json_set(JsonPath::new("data").key("a b"), "x\"y")
+                .r#where(Wheres::equal("id", 1))
+                .into_sql_seg(DbType::Sqlite)
+                .unwrap();
+            conn.execute(&seg.seg, rusqlite::params_from_iter(seg.values))
+                .unwrap();
+
+            let seg = reader().into_sql_seg(DbType::Sqlite).unwrap();
+            let tag: String = conn
+                .query_row(&seg.seg, rusqlite::params_from_iter(seg.values), |r| {
+                    r.get(0)
+                })
+                .unwrap();
+            assert_eq!("x", tag);
+            let v: String = conn
+                .query_row("select json_extract(data, '$.\"a b\"') from doc", [], |r| {
+                    r.get(0)
+                })
+                .unwrap();
+            assert_eq!("x\"y", v);
+
+            conn.execute(r#"update doc set data = '{"a\"b": 3}'"#, [])
+                .unwrap();
+            let sql = format!(
+                "select {} from doc",
+                JsonPath::new("data").key("a\"b").to_sql(DbType::Sqlite)
+            );
+            let v: i64 = conn.query_row(&sql, [], |r| r.get(0)).unwrap();
+            assert_eq!(3, v);
+        }
+    }
+}
@@ -1,23 +1,49 @@
+mod audit;
 mod create_table;
 mod db_type;
+mod executor;
+mod filter;
+mod filter_parser;
+mod full_text;
+mod json_path;
+#[cfg(feature = "postgres")]
+mod pg_copy;
 mod place_hoder;
+mod query_plan;
 mod sql_builder;
 mod sql_deleter;
 mod sql_inserter;
+mod sql_projection;
+mod sql_template;
 mod sql_updater;
 mod sql_value;
 mod tablefield;
+mod tenant;
+mod transfer;
 mod wheres;
 
+pub use audit::*;
 pub use create_table::*;
 pub use db_type::*;
+pub use executor::*;
+pub use filter::*;
+pub use filter_parser::*;
+pub use full_text::*;
+pub use json_path::*;
+#[cfg(feature = "postgres")]
+pub use pg_copy::*;
 pub use place_hoder::*;
+pub use query_plan::*;
 pub use sql_builder::*;
 pub use sql_deleter::*;
 pub use sql_inserter::*;
+pub use sql_projection::*;
+pub use sql_template::*;
 pub use sql_updater::*;
 pub use sql_value::*;
 pub use tablefield::*;
+pub use tenant::*;
+pub use transfer::*;
 pub use wheres::*;
 
 use thiserror::Error;
@@ -42,7 +68,9 @@ pub trait IntoSqlSeg<'a>: Send {
     {
         match db_type {
             DbType::Sqlite => self.into_sql_seg2(db_type, &mut PlaceHolderType::QustionMark),
-            DbType::Postgres => self.into_sql_seg2(db_type, &mut PlaceHolderType::DollarNumber(0)),
+            DbType::Postgres | DbType::DuckDb => {
+                self.into_sql_seg2(db_type, &mut PlaceHolderType::DollarNumber(0))
+            }
         }
     }
 
@@ -79,4 +107,13 @@ pub enum ChinSqlError {
     TransformError(String),
     #[error("FilterBuildError {0}")]
     FilterBuildError(String),
+    #[error("ExecuteError {0}")]
+    ExecuteError(Box<dyn std::error::Error + Send + Sync>),
+    #[error("UnexpectedRowCount {0}")]
+    UnexpectedRowCount(usize),
+    #[error("VersionConflict {0}")]
+    VersionConflict(String),
+    /// A tenant scoped table, or one of its columns, was queried without a [`TenantScope`].
+    #[error("MissingTenantScope {0}")]
+    MissingTenantScope(String),
 }
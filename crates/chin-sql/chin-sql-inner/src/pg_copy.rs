@@ -0,0 +1,584 @@
+use std::{borrow::Cow, fmt::Write as _, sync::Arc};
+
+use bytes::{BufMut, BytesMut};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat};
+use postgres_types::{FromSql, IsNull, ToSql, Type};
+
+use crate::{ChinSqlError, CreateTableSql, LogicFieldType, SqlValue, SqlValueRow, SqlValueStatic};
+
+const BINARY_SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyFormat {
+    /// Tab separated lines, `\N` is null.
+    Text,
+    Binary,
+}
+
+/// Encodes rows for `COPY ... FROM STDIN` and decodes the output of `COPY ... TO STDOUT`
+/// of postgres, without a connection.
+#[derive(Clone, Debug)]
+pub struct PgCopy {
+    columns: Arc<[Arc<str>]>,
+    types: Vec<LogicFieldType>,
+    format: CopyFormat,
+}
+
+fn pg_type(kind: LogicFieldType) -> Type {
+    match kind {
+        LogicFieldType::Bool => Type::BOOL,
+        LogicFieldType::I8 => Type::CHAR,
+        LogicFieldType::I16 => Type::INT2,
+        LogicFieldType::I32 => Type::INT4,
+        LogicFieldType::I64 => Type::INT8,
+        LogicFieldType::F64 => Type::FLOAT8,
+        LogicFieldType::Varchar(..) => Type::VARCHAR,
+        LogicFieldType::Text => Type::TEXT,
+        LogicFieldType::Blob => Type::BYTEA,
+        LogicFieldType::Timestamptz => Type::TIMESTAMPTZ,
+        LogicFieldType::Timestamp => Type::TIMESTAMP,
+    }
+}
+
+fn copy_error<S: Into<String>>(message: S) -> ChinSqlError {
+    ChinSqlError::TransformError(message.into())
+}
+
+impl PgCopy {
+    pub fn new<C, S>(columns: C, types: Vec<LogicFieldType>, format: CopyFormat) -> Self
+    where
+        C: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            columns: columns.into_iter().map(|c| Arc::from(c.as_ref())).collect(),
+            types,
+            format,
+        }
+    }
+
+    /// All columns of the table in their declared order.
+    pub fn table(table: &CreateTableSql, format: CopyFormat) -> Self {
+        Self::new(
+            table.fields.iter().map(|f| f.name),
+            table.fields.iter().map(|f| f.kind).collect(),
+            format,
+        )
+    }
+
+    fn options(&self) -> &'static str {
+        match self.format {
+            CopyFormat::Text => "",
+            CopyFormat::Binary => " (FORMAT binary)",
+        }
+    }
+
+    pub fn copy_in_sql(&self, table: &str) -> String {
+        format!(
+            "COPY {table} ({}) FROM STDIN{}",
+            self.columns.join(", "),
+            self.options()
+        )
+    }
+
+    pub fn copy_out_sql(&self, table: &str) -> String {
+        format!(
+            "COPY {table} ({}) TO STDOUT{}",
+            self.columns.join(", "),
+            self.options()
+        )
+    }
+
+    /// The binary signature, nothing for text.
+    pub fn encode_header(&self, out: &mut BytesMut) {
+        if self.format == CopyFormat::Binary {
+            out.put_slice(BINARY_SIGNATURE);
+            out.put_i32(0);
+            out.put_i32(0);
+        }
+    }
+
+    pub fn encode_trailer(&self, out: &mut BytesMut) {
+        if self.format == CopyFormat::Binary {
+            out.put_i16(-1);
+        }
+    }
+
+    pub fn encode_row(&self, row: &[SqlValue], out: &mut BytesMut) -> Result<(), ChinSqlError> {
+        if row.len() != self.types.len() {
+            return Err(copy_error(format!(
+                "copy {} columns from a row of {} values",
+                self.types.len(),
+                row.len()
+            )));
+        }
+        match self.format {
+            CopyFormat::Binary => {
+                out.put_i16(row.len() as i16);
+                for (value, kind) in row.iter().zip(&self.types) {
+                    encode_binary(value, &pg_type(*kind), out)?;
+                }
+            }
+            CopyFormat::Text => {
+                let mut line = String::new();
+                for (i, (value, kind)) in row.iter().zip(&self.types).enumerate() {
+                    if i > 0 {
+                        line.push('\t');
+                    }
+                    encode_text(value, *kind, &mut line)?;
+                }
+                line.push('\n');
+                out.put_slice(line.as_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// Header, rows and trailer.
+    pub fn encode<'v, I>(&self, rows: I) -> Result<BytesMut, ChinSqlError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[SqlValue<'v>]>,
+    {
+        let mut out = BytesMut::new();
+        self.encode_header(&mut out);
+        for row in rows {
+            self.encode_row(row.as_ref(), &mut out)?;
+        }
+        self.encode_trailer(&mut out);
+        Ok(out)
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<SqlValueRow>, ChinSqlError> {
+        let rows = match self.format {
+            CopyFormat::Binary => self.decode_binary(data)?,
+            CopyFormat::Text => self.decode_text(data)?,
+        };
+        Ok(rows
+            .into_iter()
+            .map(|values| SqlValueRow::new(self.columns.clone(), values))
+            .collect())
+    }
+
+    fn decode_binary(&self, data: &[u8]) -> Result<Vec<Vec<SqlValueStatic>>, ChinSqlError> {
+        let mut reader = BinaryReader { data, pos: 0 };
+        if reader.take(BINARY_SIGNATURE.len())? != BINARY_SIGNATURE {
+            return Err(copy_error("invalid copy signature"));
+        }
+        let _flags = reader.i32()?;
+        let extension = reader.i32()?;
+        reader.take(usize::try_from(extension).map_err(|_| copy_error("invalid extension"))?)?;
+
+        let types: Vec<Type> = self.types.iter().map(|k| pg_type(*k)).collect();
+        let mut rows = vec![];
+        loop {
+            let count = reader.i16()?;
+            if count == -1 {
+                break;
+            }
+            if count as usize != types.len() {
+                return Err(copy_error(format!(
+                    "copy {} columns into a row of {count} values",
+                    types.len()
+                )));
+            }
+            let row = types
+                .iter()
+                .map(|ty| {
+                    let len = reader.i32()?;
+                    if len < 0 {
+                        return SqlValueStatic::from_sql_null(ty)
+                            .map_err(ChinSqlError::ExecuteError);
+                    }
+                    let raw = reader.take(len as usize)?;
+                    SqlValueStatic::from_sql(ty, raw).map_err(ChinSqlError::ExecuteError)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    fn decode_text(&self, data: &[u8]) -> Result<Vec<Vec<SqlValueStatic>>, ChinSqlError> {
+        let text = std::str::from_utf8(data).map_err(|err| copy_error(err.to_string()))?;
+        text.split_terminator('\n')
+            .enumerate()
+            .map(|(n, line)| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                if fields.len() != self.types.len() {
+                    return Err(copy_error(format!(
+                        "line {}: copy {} columns into a row of {} values",
+                        n + 1,
+                        self.types.len(),
+                        fields.len()
+                    )));
+                }
+                fields
+                    .iter()
+                    .zip(&self.types)
+                    .map(|(field, kind)| {
+                        decode_text(field, *kind)
+                            .map_err(|err| copy_error(format!("line {}: {err}", n + 1)))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn encode_binary(value: &SqlValue, ty: &Type, out: &mut BytesMut) -> Result<(), ChinSqlError> {
+    if matches!(value, SqlValue::Null(_) | SqlValue::NullUnknown) {
+        out.put_i32(-1);
+        return Ok(());
+    }
+    if let SqlValue::Slot(name) = value {
+        return Err(copy_error(format!("slot {name} is not bound")));
+    }
+    let len_at = out.len();
+    out.put_i32(0);
+    // chrono only maps naive date times to `timestamp`
+    let naive = match value {
+        SqlValue::Utc(v) if *ty == Type::TIMESTAMP => Some(v.naive_utc()),
+        SqlValue::FixedOffset(v) if *ty == Type::TIMESTAMP => Some(v.naive_utc()),
+        _ => None,
+    };
+    let written = match &naive {
+        Some(naive) => naive.to_sql_checked(ty, out),
+        None => <&(dyn ToSql + Sync + Send)>::from(value).to_sql_checked(ty, out),
+    }
+    .map_err(ChinSqlError::ExecuteError)?;
+    let len = match written {
+        IsNull::Yes => -1,
+        IsNull::No => i32::try_from(out.len() - len_at - 4)
+            .map_err(|_| copy_error("copy value is too large"))?,
+    };
+    if len < 0 {
+        out.truncate(len_at + 4);
+    }
+    out[len_at..len_at + 4].copy_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+fn push_escaped(s: &str, line: &mut String) {
+    for c in s.chars() {
+        match c {
+            '\\' => line.push_str("\\\\"),
+            '\t' => line.push_str("\\t"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            c => line.push(c),
+        }
+    }
+}
+
+/// The text form of the postgres `"char"` type, which reads and prints the zero byte as an
+/// empty string and bytes above 127 as `\ooo`.
+fn push_char(b: u8, line: &mut String) {
+    match b {
+        0 => {}
+        b'\\' => line.push_str("\\\\"),
+        0x20..=0x7e => line.push(b as char),
+        // an octal escape of copy, which turns into the byte itself
+        0x01..=0x1f | 0x7f => {
+            let _ = write!(line, "\\{b:03o}");
+        }
+        // the escape of `"char"`, its backslash escaped for copy
+        _ => {
+            let _ = write!(line, "\\\\{b:03o}");
+        }
+    }
+}
+
+fn encode_text(
+    value: &SqlValue,
+    kind: LogicFieldType,
+    line: &mut String,
+) -> Result<(), ChinSqlError> {
+    match value {
+        SqlValue::Null(_) | SqlValue::NullUnknown => line.push_str("\\N"),
+        SqlValue::Bool(v) => line.push(if *v { 't' } else { 'f' }),
+        SqlValue::I8(v) => push_char(*v as u8, line),
+        SqlValue::I16(v) => line.push_str(&v.to_string()),
+        SqlValue::I32(v) => line.push_str(&v.to_string()),
+        SqlValue::I64(v) => line.push_str(&v.to_string()),
+        SqlValue::F64(v) if v.is_nan() => line.push_str("NaN"),
+        SqlValue::F64(v) if v.is_infinite() => {
+            line.push_str(if *v > 0.0 { "Infinity" } else { "-Infinity" })
+        }
+        SqlValue::F64(v) => line.push_str(&v.to_string()),
+        SqlValue::Str(v) => push_escaped(v, line),
+        SqlValue::FixedOffset(v) if kind == LogicFieldType::Timestamp => {
+            let _ = write!(line, "{}", v.naive_utc().format("%Y-%m-%d %H:%M:%S%.f"));
+        }
+        SqlValue::Utc(v) if kind == LogicFieldType::Timestamp => {
+            let _ = write!(line, "{}", v.naive_utc().format("%Y-%m-%d %H:%M:%S%.f"));
+        }
+        SqlValue::FixedOffset(v) => line.push_str(&v.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+        SqlValue::Utc(v) => line.push_str(&v.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+        // bytea hex format, its backslash is escaped as well
+        SqlValue::Blob(v) => {
+            line.push_str("\\\\x");
+            for b in v.iter() {
+                let _ = write!(line, "{b:02x}");
+            }
+        }
+        SqlValue::Slot(name) => return Err(copy_error(format!("slot {name} is not bound"))),
+    }
+    Ok(())
+}
+
+/// The bytes of a field, the escapes of copy stand for bytes, e.g. `\303\251` for `é`.
+fn unescape(field: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(field.len());
+    let mut chars = field.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push(0x8),
+            Some('f') => out.push(0xc),
+            Some('n') => out.push(b'\n'),
+            Some('r') => out.push(b'\r'),
+            Some('t') => out.push(b'\t'),
+            Some('v') => out.push(0xb),
+            Some('x') => {
+                let mut hex = String::new();
+                while hex.len() < 2 && chars.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+                    hex.extend(chars.next());
+                }
+                let b = u8::from_str_radix(&hex, 16)
+                    .map_err(|_| format!("invalid escape in {field}"))?;
+                out.push(b);
+            }
+            Some(d @ '0'..='7') => {
+                let mut oct = String::from(d);
+                while oct.len() < 3 && chars.peek().is_some_and(|c| ('0'..='7').contains(c)) {
+                    oct.extend(chars.next());
+                }
+                let b = u8::from_str_radix(&oct, 8)
+                    .map_err(|_| format!("invalid escape in {field}"))?;
+                out.push(b);
+            }
+            Some(c) => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            None => return Err(format!("dangling backslash in {field}")),
+        }
+    }
+    Ok(out)
+}
+
+/// A `"char"`, its byte or the octal escape of the bytes above 127.
+fn decode_char(bytes: &[u8]) -> Result<SqlValueStatic, String> {
+    let invalid = || format!("{:?} is not a valid I8", String::from_utf8_lossy(bytes));
+    Ok(match bytes {
+        [b] => SqlValue::I8(*b as i8),
+        [] => SqlValue::I8(0),
+        [b'\\', octal @ ..] if octal.len() == 3 => {
+            let octal = std::str::from_utf8(octal).map_err(|_| invalid())?;
+            SqlValue::I8(u8::from_str_radix(octal, 8).map_err(|_| invalid())? as i8)
+        }
+        _ => return Err(invalid()),
+    })
+}
+
+fn decode_text(field: &str, kind: LogicFieldType) -> Result<SqlValueStatic, String> {
+    if field == "\\N" {
+        return Ok(SqlValue::Null(kind));
+    }
+    let bytes = unescape(field)?;
+    let text = match kind {
+        LogicFieldType::I8 => return decode_char(&bytes),
+        _ => String::from_utf8(bytes).map_err(|_| format!("invalid utf-8 in {field}"))?,
+    };
+    let invalid = || format!("{text:?} is not a valid {kind:?}");
+    Ok(match kind {
+        LogicFieldType::Bool => match text.as_str() {
+            "t" => SqlValue::Bool(true),
+            "f" => SqlValue::Bool(false),
+            _ => return Err(invalid()),
+        },
+        LogicFieldType::I8 => unreachable!("decoded as bytes"),
+        LogicFieldType::I16 => SqlValue::I16(text.parse().map_err(|_| invalid())?),
+        LogicFieldType::I32 => SqlValue::I32(text.parse().map_err(|_| invalid())?),
+        LogicFieldType::I64 => SqlValue::I64(text.parse().map_err(|_| invalid())?),
+        LogicFieldType::F64 => SqlValue::F64(match text.as_str() {
+            "NaN" => f64::NAN,
+            "Infinity" => f64::INFINITY,
+            "-Infinity" => f64::NEG_INFINITY,
+            text => text.parse().map_err(|_| invalid())?,
+        }),
+        LogicFieldType::Varchar(..) | LogicFieldType::Text => SqlValue::Str(Cow::Owned(text)),
+        LogicFieldType::Blob => {
+            let hex = text.strip_prefix("\\x").ok_or_else(invalid)?;
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| {
+                    hex.get(i..i + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(invalid)?;
+            SqlValue::Blob(Cow::Owned(bytes))
+        }
+        LogicFieldType::Timestamptz => SqlValue::FixedOffset(
+            DateTime::<FixedOffset>::parse_from_rfc3339(&text)
+                .or_else(|_| DateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f%#z"))
+                .map_err(|_| invalid())?,
+        ),
+        LogicFieldType::Timestamp => SqlValue::Utc(
+            NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f")
+                .map_err(|_| invalid())?
+                .and_utc(),
+        ),
+    })
+}
+
+struct BinaryReader<'d> {
+    data: &'d [u8],
+    pos: usize,
+}
+
+impl<'d> BinaryReader<'d> {
+    fn take(&mut self, len: usize) -> Result<&'d [u8], ChinSqlError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| copy_error("unexpected end of copy data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn i16(&mut self) -> Result<i16, ChinSqlError> {
+        Ok(i16::from_be_bytes(
+            self.take(2)?.try_into().unwrap_or_default(),
+        ))
+    }
+
+    fn i32(&mut self) -> Result<i32, ChinSqlError> {
+        Ok(i32::from_be_bytes(
+            self.take(4)?.try_into().unwrap_or_default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use chrono::DateTime;
+
+    use crate::{LogicFieldType, SqlValue, str_type::LengthUnit};
+
+    use super::{CopyFormat, PgCopy};
+
+    fn copy(format: CopyFormat) -> PgCopy {
+        PgCopy::new(
+            ["id", "name", "vip", "score", "at", "data"],
+            vec![
+                LogicFieldType::I32,
+                LogicFieldType::Varchar(16, LengthUnit::Chars),
+                LogicFieldType::Bool,
+                LogicFieldType::F64,
+                LogicFieldType::Timestamptz,
+                LogicFieldType::Blob,
+            ],
+            format,
+        )
+    }
+
+    fn rows() -> Vec<Vec<SqlValue<'static>>> {
+        let at = DateTime::parse_from_rfc3339("2024-05-01T00:00:00.5+00:00").unwrap();
+        vec![
+            vec![
+                SqlValue::I32(1),
+                SqlValue::Str(Cow::Borrowed("a\tb\\c\nd")),
+                SqlValue::Bool(true),
+                SqlValue::F64(1.5),
+                SqlValue::FixedOffset(at),
+                SqlValue::Blob(Cow::Borrowed(b"\x00\xff")),
+            ],
+            vec![
+                SqlValue::I32(2),
+                SqlValue::Str(Cow::Borrowed("")),
+                SqlValue::Bool(false),
+                SqlValue::Null(LogicFieldType::F64),
+                SqlValue::FixedOffset(at),
+                SqlValue::Null(LogicFieldType::Blob),
+            ],
+        ]
+    }
+
+    fn assert_same(expected: &[Vec<SqlValue>], actual: &[crate::SqlValueRow]) {
+        assert_eq!(expected.len(), actual.len());
+        for (expected, actual) in expected.iter().zip(actual) {
+            assert_eq!(format!("{expected:?}"), format!("{:?}", actual.values()));
+        }
+    }
+
+    #[test]
+    fn copy_text() {
+        let copy = copy(CopyFormat::Text);
+        assert_eq!(
+            "COPY member (id, name, vip, score, at, data) FROM STDIN",
+            copy.copy_in_sql("member")
+        );
+        let data = copy.encode(rows()).unwrap();
+        assert_eq!(
+            "1\ta\\tb\\\\c\\nd\tt\t1.5\t2024-05-01T00:00:00.500+00:00\t\\\\x00ff\n2\t\tf\t\\N\t2024-05-01T00:00:00.500+00:00\t\\N\n",
+            std::str::from_utf8(&data).unwrap()
+        );
+        assert_same(&rows(), &copy.decode(&data).unwrap());
+
+        // as printed by postgres
+        let decoded = copy
+            .decode(b"3\tx\tt\t2\t2024-05-01 08:00:00+08\t\\\\x6869\n")
+            .unwrap();
+        assert_eq!(1, decoded.len());
+        assert!(copy.decode(b"3\tx\n").is_err());
+
+        let chars = PgCopy::new(["flag"], vec![LogicFieldType::I8], CopyFormat::Text);
+        let rows: Vec<Vec<SqlValue>> = [-1, 0, 65, 1, 92, -128, 127]
+            .into_iter()
+            .map(|v: i8| vec![SqlValue::I8(v)])
+            .collect();
+        let data = chars.encode(rows.clone()).unwrap();
+        assert_eq!(
+            "\\\\377\n\nA\n\\001\n\\\\\n\\\\200\n\\177\n",
+            std::str::from_utf8(&data).unwrap()
+        );
+        assert_same(&rows, &chars.decode(&data).unwrap());
+        // the raw byte of a char and octal escapes of utf-8
+        assert_same(
+            &[vec![SqlValue::I8(-128)]],
+            &chars.decode(b"\\200\n").unwrap(),
+        );
+        let names = PgCopy::new(["name"], vec![LogicFieldType::Text], CopyFormat::Text);
+        assert_same(
+            &[vec![SqlValue::from("é")]],
+            &names.decode(b"\\303\\251\n").unwrap(),
+        );
+        assert!(names.decode(b"\\377\n").is_err());
+    }
+
+    #[test]
+    fn copy_binary() {
+        let copy = copy(CopyFormat::Binary);
+        assert_eq!(
+            "COPY member (id, name, vip, score, at, data) TO STDOUT (FORMAT binary)",
+            copy.copy_out_sql("member")
+        );
+        let data = copy.encode(rows()).unwrap();
+        assert_eq!(b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0", &data[..19]);
+        // column count and the int4 id
+        assert_eq!(&[0, 6, 0, 0, 0, 4, 0, 0, 0, 1], &data[19..29]);
+        assert_eq!(&[0xff, 0xff], &data[data.len() - 2..]);
+        // timestamptz comes back in utc
+        assert_same(&rows(), &copy.decode(&data).unwrap());
+
+        assert!(copy.decode(&data[..data.len() - 2]).is_err());
+        assert!(copy.encode([vec![SqlValue::I32(1)]]).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt::Display;
+
+use crate::{ChinSqlError, DbType, Froms, IntoSqlSeg, SqlExecutor, SqlReader, SqlSeg};
+
+/// How a step of a sqlite query plan reads its table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlanAccess {
+    FullScan,
+    /// `USING AUTOMATIC COVERING INDEX`, an index which sqlite builds for the query by
+    /// reading the whole table.
+    AutomaticIndex,
+    Index {
+        name: String,
+        covering: bool,
+    },
+    PrimaryKey,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlanKind {
+    /// `SCAN t`, `SCAN t USING COVERING INDEX i`
+    Scan {
+        table: String,
+        access: PlanAccess,
+    },
+    /// `SEARCH t USING INDEX i (a=?)`
+    Search {
+        table: String,
+        access: PlanAccess,
+    },
+    /// `USE TEMP B-TREE FOR ORDER BY`, the purpose is `ORDER BY`.
+    TempBTree {
+        purpose: String,
+    },
+    Other,
+}
+
+impl PlanKind {
+    /// Parses the `detail` column of `EXPLAIN QUERY PLAN`, the table is the alias if the
+    /// query has one.
+    pub fn parse(detail: &str) -> Self {
+        if let Some(purpose) = detail.strip_prefix("USE TEMP B-TREE FOR ") {
+            return Self::TempBTree {
+                purpose: purpose.to_owned(),
+            };
+        }
+        if let Some((purpose, _)) = detail.split_once(" USING TEMP B-TREE") {
+            return Self::TempBTree {
+                purpose: purpose.to_owned(),
+            };
+        }
+
+        let (search, rest) = match (detail.strip_prefix("SCAN "), detail.strip_prefix("SEARCH ")) {
+            (Some(rest), _) => (false, rest),
+            (_, Some(rest)) => (true, rest),
+            _ => return Self::Other,
+        };
+        // sqlite before 3.36 printed `SCAN TABLE t AS a`
+        let rest = rest.strip_prefix("TABLE ").unwrap_or(rest);
+        if rest == "CONSTANT ROW" || rest.starts_with('(') {
+            return Self::Other;
+        }
+        let (table, using) = match rest.split_once(' ') {
+            Some((table, using)) => (table, using),
+            None => (rest, ""),
+        };
+        let table = match using.strip_prefix("AS ") {
+            Some(alias) => alias.split(' ').next().unwrap_or(table),
+            None => table,
+        };
+
+        let index = |name: &str, covering| PlanAccess::Index {
+            name: name.split(' ').next().unwrap_or(name).to_owned(),
+            covering,
+        };
+        let access = match using.split_once("USING ").map(|(_, u)| u) {
+            Some(u) if u.starts_with("AUTOMATIC ") => PlanAccess::AutomaticIndex,
+            Some(u) if u.starts_with("COVERING INDEX ") => index(&u[15..], true),
+            Some(u) if u.starts_with("INDEX ") => index(&u[6..], false),
+            Some(u) if u.contains("PRIMARY KEY") => PlanAccess::PrimaryKey,
+            _ => match using.split_once("VIRTUAL TABLE INDEX ") {
+                Some((_, idx)) => index(idx, false),
+                None => PlanAccess::FullScan,
+            },
+        };
+
+        let table = table.to_owned();
+        match search {
+            true => Self::Search { table, access },
+            false => Self::Scan { table, access },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanStep {
+    pub id: i64,
+    pub parent: i64,
+    pub detail: String,
+    pub kind: PlanKind,
+}
+
+/// The parsed output of sqlite's `EXPLAIN QUERY PLAN`, see [`QueryPlan::explain`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryPlan {
+    pub steps: Vec<PlanStep>,
+    /// `(alias, table)` of the reader given to [`QueryPlan::explain_reader`].
+    pub aliases: Vec<(String, String)>,
+}
+
+impl QueryPlan {
+    /// Runs `EXPLAIN QUERY PLAN` for a seg built for sqlite, e.g. on an actor sqlite client.
+    pub async fn explain<E: SqlExecutor>(
+        executor: &E,
+        seg: SqlSeg<'_>,
+    ) -> Result<Self, ChinSqlError> {
+        if executor.db_type() != DbType::Sqlite {
+            return Err(ChinSqlError::BuilderSqlError(format!(
+                "query plans are only parsed for sqlite, not {:?}",
+                executor.db_type()
+            )));
+        }
+        let seg = SqlSeg::of(format!("explain query plan {}", seg.seg), seg.values);
+        let steps = executor
+            .query(seg)
+            .await?
+            .into_iter()
+            .map(|row| {
+                let detail: String = row.try_get("detail")?;
+                Ok(PlanStep {
+                    id: row.try_get("id")?,
+                    parent: row.try_get("parent")?,
+                    kind: PlanKind::parse(&detail),
+                    detail,
+                })
+            })
+            .collect::<Result<_, ChinSqlError>>()?;
+        Ok(Self {
+            steps,
+            aliases: vec![],
+        })
+    }
+
+    /// Like [`QueryPlan::explain`], the aliases of the tables of the reader are kept to
+    /// resolve the scans of the plan back to table names.
+    pub async fn explain_reader<E: SqlExecutor>(
+        executor: &E,
+        reader: SqlReader<'_>,
+    ) -> Result<Self, ChinSqlError> {
+        let mut aliases = vec![];
+        collect_aliases(reader.froms(), &mut aliases);
+        let mut plan = Self::explain(executor, reader.into_sql_seg(DbType::Sqlite)?).await?;
+        plan.aliases = aliases;
+        Ok(plan)
+    }
+
+    /// The table of an alias in [`QueryPlan::aliases`], else the name itself.
+    pub fn table<'p>(&'p self, name: &'p str) -> &'p str {
+        self.aliases
+            .iter()
+            .find(|(alias, _)| alias == name)
+            .map_or(name, |(_, table)| table.as_str())
+    }
+
+    /// Tables, or their aliases, which are read without any index, including the ones read
+    /// to build an automatic index.
+    pub fn full_scans(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .filter_map(|step| match &step.kind {
+                PlanKind::Scan {
+                    table,
+                    access: PlanAccess::FullScan | PlanAccess::AutomaticIndex,
+                }
+                | PlanKind::Search {
+                    table,
+                    access: PlanAccess::AutomaticIndex,
+                } => Some(table.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The full scans of `large_tables`, e.g. to log them at runtime or fail a test.
+    ///
+    /// Sqlite names the scans by alias, they only match the table names of `large_tables`
+    /// for a plan of [`QueryPlan::explain_reader`].
+    pub fn large_full_scans(&self, large_tables: &[&str]) -> Vec<&str> {
+        self.full_scans()
+            .into_iter()
+            .map(|name| self.table(name))
+            .filter(|table| large_tables.contains(table))
+            .collect()
+    }
+
+    pub fn uses_index(&self, index: &str) -> bool {
+        self.steps.iter().any(|step| match &step.kind {
+            PlanKind::Scan {
+                access: PlanAccess::Index { name, .. },
+                ..
+            }
+            | PlanKind::Search {
+                access: PlanAccess::Index { name, .. },
+                ..
+            } => name == index,
+            _ => false,
+        })
+    }
+
+    /// Purposes of the temp b-trees, e.g. `ORDER BY` or `DISTINCT`.
+    pub fn temp_btrees(&self) -> Vec<&str> {
+        self.steps
+            .iter()
+            .filter_map(|step| match &step.kind {
+                PlanKind::TempBTree { purpose } => Some(purpose.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn fmt_children(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        parent: i64,
+        depth: usize,
+    ) -> std::fmt::Result {
+        for step in self.steps.iter().filter(|s| s.parent == parent) {
+            writeln!(f, "{:indent$}{}", "", step.detail, indent = depth * 2)?;
+            self.fmt_children(f, step.id, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn collect_aliases(froms: &Froms<'_>, aliases: &mut Vec<(String, String)>) {
+    match froms {
+        Froms::Table { table_name, alias } => {
+            aliases.push((alias.to_string(), table_name.to_string()));
+        }
+        Froms::SubQuery { table, .. } => collect_aliases(table.froms(), aliases),
+        Froms::Union { table, .. } => table
+            .iter()
+            .for_each(|reader| collect_aliases(reader.froms(), aliases)),
+        Froms::Joins(joins) => {
+            collect_aliases(&joins.base, aliases);
+            for join in &joins.joins {
+                collect_aliases(&join.table, aliases);
+            }
+        }
+    }
+}
+
+/// Prints the plan as an indented tree like the sqlite shell.
+impl Display for QueryPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_children(f, 0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DbType, Froms, IntoSqlSeg, SqlExecutor, SqlReader, SqlTypedField, str_type::Text};
+
+    use super::{PlanAccess, PlanKind, PlanStep, QueryPlan};
+
+    #[test]
+    fn parse_detail() {
+        assert_eq!(
+            PlanKind::Search {
+                table: "x".to_owned(),
+                access: PlanAccess::Index {
+                    name: "t_a".to_owned(),
+                    covering: false
+                }
+            },
+            PlanKind::parse("SEARCH x USING INDEX t_a (a=?)")
+        );
+        assert_eq!(
+            PlanKind::Scan {
+                table: "x".to_owned(),
+                access: PlanAccess::Index {
+                    name: "t_a".to_owned(),
+                    covering: true
+                }
+            },
+            PlanKind::parse("SCAN TABLE t AS x USING COVERING INDEX t_a")
+        );
+        assert_eq!(
+            PlanKind::Search {
+                table: "t".to_owned(),
+                access: PlanAccess::PrimaryKey
+            },
+            PlanKind::parse("SEARCH t USING INTEGER PRIMARY KEY (rowid=?)")
+        );
+        assert_eq!(
+            PlanKind::Scan {
+                table: "u".to_owned(),
+                access: PlanAccess::FullScan
+            },
+            PlanKind::parse("SCAN u")
+        );
+        assert_eq!(
+            PlanKind::TempBTree {
+                purpose: "GROUP BY".to_owned()
+            },
+            PlanKind::parse("USE TEMP B-TREE FOR GROUP BY")
+        );
+        assert_eq!(
+            PlanKind::TempBTree {
+                purpose: "UNION".to_owned()
+            },
+            PlanKind::parse("UNION USING TEMP B-TREE")
+        );
+        let automatic = PlanKind::parse("SEARCH u USING AUTOMATIC COVERING INDEX (a=?)");
+        assert_eq!(
+            PlanKind::Search {
+                table: "u".to_owned(),
+                access: PlanAccess::AutomaticIndex
+            },
+            automatic
+        );
+        let plan = QueryPlan {
+            steps: vec![PlanStep {
+                id: 1,
+                parent: 0,
+                detail: String::new(),
+                kind: automatic,
+            }],
+            aliases: vec![],
+        };
+        assert_eq!(vec!["u"], plan.full_scans());
+        assert_eq!(PlanKind::Other, PlanKind::parse("SCAN CONSTANT ROW"));
+        assert_eq!(PlanKind::Other, PlanKind::parse("COMPOUND QUERY"));
+    }
+
+    #[tokio::test]
+    async fn explain() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        SqlExecutor::execute(
+            &conn,
+            "create table plan(id integer primary key, a integer, b text)",
+        )
+        .await
+        .unwrap();
+        SqlExecutor::execute(&conn, "create index plan_a on plan(a)")
+            .await
+            .unwrap();
+
+        let a = SqlTypedField::<i64>::new("p", "a");
+        let b = SqlTypedField::<Text>::new("p", "b");
+        let froms = || Froms::Table {
+            table_name: "plan",
+            alias: "p",
+        };
+        let reader = SqlReader::builder(vec![b.erased()], froms())
+            .wheres(a.v_eq(1))
+            .build();
+        let plan = QueryPlan::explain(&conn, reader.into_sql_seg(DbType::Sqlite).unwrap())
+            .await
+            .unwrap();
+        assert!(plan.uses_index("plan_a"));
+        assert!(plan.full_scans().is_empty());
+
+        let reader = SqlReader::builder(vec![b.erased()], froms())
+            .wheres(b.v_ne("x".to_owned()))
+            .order_by([b.asc()])
+            .build();
+        let plan = QueryPlan::explain_reader(&conn, reader).await.unwrap();
+        assert_eq!(vec!["p"], plan.full_scans());
+        assert_eq!(vec!["plan"], plan.large_full_scans(&["plan"]));
+        assert!(plan.large_full_scans(&["p"]).is_empty());
+        assert_eq!(vec!["ORDER BY"], plan.temp_btrees());
+        assert_eq!("SCAN p\nUSE TEMP B-TREE FOR ORDER BY\n", plan.to_string());
+    }
+}
@@ -1,16 +1,25 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, marker::PhantomData};
 
-use crate::{ChinSqlError, DbType, IntoSqlSeg, SegOrVal, SqlField, SqlSeg, SqlTypedField};
+use crate::{
+    ChinSqlError, DbType, IntoSqlSeg, JsonPath, SegOrVal, SqlField, SqlSeg, SqlTypedField,
+    TenantScope,
+    tenant::{and_scoped, scope_froms, table_columns},
+};
 
 use super::{place_hoder::PlaceHolderType, sql_value::SqlValue, wheres::Wheres};
 
 pub trait CustomSqlSeg<'a>: Send {
-    fn build(&self, value_type: &mut PlaceHolderType) -> Option<SqlSeg<'a>>;
+    fn build(
+        &self,
+        db_type: DbType,
+        value_type: &mut PlaceHolderType,
+    ) -> Result<Option<SqlSeg<'a>>, ChinSqlError>;
 }
 
 enum SqlBuilderSeg<'a> {
     Where(Wheres<'a>),
     LimitOffset(LimitOffset),
+    LimitSlots,
     Comma(Vec<&'a str>),
     SegOrVal(SegOrVal<'a>),
     RawOwned(String),
@@ -19,10 +28,20 @@ enum SqlBuilderSeg<'a> {
         alias: &'a str,
         query: SqlBuilder<'a>,
     },
+    GroupBy(GroupBy<'a>),
+    Clause(&'static str, Wheres<'a>),
+    Fields(Vec<SqlField<'a>>),
+    OrderBy(Vec<OrderBy<'a>>),
+    Join(JoinTable<'a>),
+    Lock(RowLock<'a>),
+    Error(ChinSqlError),
 }
 
 pub struct SqlBuilder<'a> {
     segs: Vec<SqlBuilderSeg<'a>>,
+    // tables of `read` / `read_all` which are not tenant scoped yet, with the tenant columns
+    // set by `tenant_columns`
+    reads: Vec<(String, Option<&'static [&'static str]>)>,
 }
 
 impl<'a> Default for SqlBuilder<'a> {
@@ -33,7 +52,10 @@ impl<'a> Default for SqlBuilder<'a> {
 
 impl<'a> SqlBuilder<'a> {
     pub fn new() -> Self {
-        Self { segs: vec![] }
+        Self {
+            segs: vec![],
+            reads: vec![],
+        }
     }
 
     pub fn read(table_name: &str, fields: &[&str]) -> Self {
@@ -43,6 +65,7 @@ impl<'a> SqlBuilder<'a> {
                 fields.join(", "),
                 table_name
             ))],
+            reads: vec![(table_name.to_owned(), None)],
         }
     }
 
@@ -51,7 +74,51 @@ impl<'a> SqlBuilder<'a> {
             segs: vec![SqlBuilderSeg::RawOwned(format!(
                 "select * from {table_name} "
             ))],
+            reads: vec![(table_name.to_owned(), None)],
+        }
+    }
+
+    /// ANDs the scope of the tables of [`SqlBuilder::read`] into the first where, e.g. of a
+    /// derived `*_reader`. The predicates are qualified with the table name, so joins added
+    /// later do not make them ambiguous.
+    pub fn scope(mut self, scope: &TenantScope<'a>) -> Self {
+        let mut wheres = vec![];
+        for (table, columns) in std::mem::take(&mut self.reads) {
+            let columns = table_columns(&table, columns).unwrap_or_default();
+            match scope.columns_wheres(&table, columns, Some(&table)) {
+                Ok(w) => wheres.push(w),
+                Err(err) => {
+                    self.segs.push(SqlBuilderSeg::Error(err));
+                    return self;
+                }
+            }
         }
+        let first = self.segs.iter_mut().find_map(|seg| match seg {
+            SqlBuilderSeg::Where(w) => Some(w),
+            _ => None,
+        });
+        match first {
+            Some(w) => {
+                let cond = std::mem::replace(w, Wheres::None);
+                *w = and_scoped(cond, wheres);
+            }
+            None => {
+                let at = self.segs.len().min(1);
+                self.segs
+                    .insert(at, SqlBuilderSeg::Where(Wheres::and(wheres)));
+            }
+        }
+        self
+    }
+
+    /// Marks `table` of [`SqlBuilder::read`] as tenant scoped by `columns` without
+    /// [`crate::register_tenant_table`], e.g. the `TENANT_COLUMNS` of a derived table.
+    pub fn tenant_columns(mut self, table: &str, columns: &'static [&'static str]) -> Self {
+        match self.reads.iter_mut().find(|(t, _)| t == table) {
+            Some((_, c)) => *c = Some(columns),
+            None => self.reads.push((table.to_owned(), Some(columns))),
+        }
+        self
     }
 
     pub fn val<T: Into<SqlValue<'a>>>(mut self, val: T) -> Self {
@@ -89,6 +156,29 @@ impl<'a> SqlBuilder<'a> {
         self
     }
 
+    /// Select list, json fields are rendered per db type.
+    pub fn fields<V: Into<Vec<SqlField<'a>>>>(mut self, fields: V) -> Self {
+        self.segs.push(SqlBuilderSeg::Fields(fields.into()));
+        self
+    }
+
+    pub fn group_by<T: Into<GroupBy<'a>>>(mut self, group_by: T) -> Self {
+        self.segs.push(SqlBuilderSeg::GroupBy(group_by.into()));
+        self
+    }
+
+    pub fn having<T: Into<Wheres<'a>>>(mut self, having: T) -> Self {
+        self.segs
+            .push(SqlBuilderSeg::Clause("having", having.into()));
+        self
+    }
+
+    /// Postgres only.
+    pub fn lock(mut self, lock: RowLock<'a>) -> Self {
+        self.segs.push(SqlBuilderSeg::Lock(lock));
+        self
+    }
+
     pub fn comma(mut self, values: Vec<&'a str>) -> Self {
         self.segs.push(SqlBuilderSeg::Comma(values));
         self
@@ -115,23 +205,16 @@ impl<'a> SqlBuilder<'a> {
         self
     }
 
-    pub fn order_by<'b, T: Into<Vec<OrderBy<'b>>>>(self, orders: T) -> Self {
-        let orders: Vec<String> = orders
-            .into()
-            .iter()
-            .filter(|e| !matches!(e, OrderBy::None))
-            .map(|e| match e {
-                OrderBy::Asc(shared_str) => format!("{} asc", shared_str),
-                OrderBy::Desc(shared_str) => format!("{} desc", shared_str),
-                OrderBy::None => unreachable!(),
-            })
-            .collect();
-        self.seg(" order by ").seg(orders.join(", "))
+    pub fn order_by<T: Into<Vec<OrderBy<'a>>>>(mut self, orders: T) -> Self {
+        self = self.seg(" order by ");
+        self.segs.push(SqlBuilderSeg::OrderBy(orders.into()));
+        self
     }
 
     pub fn merge<SB: Into<SqlBuilder<'a>>>(mut self, other: SB) -> Self {
-        let SqlBuilder { segs } = other.into();
+        let SqlBuilder { segs, reads } = other.into();
         self.segs.extend(segs);
+        self.reads.extend(reads);
         self
     }
 }
@@ -142,12 +225,163 @@ pub struct LimitOffset {
     pub offset: Option<usize>,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum LockStrength {
+    Update,
+    NoKeyUpdate,
+    Share,
+    KeyShare,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum LockWait {
+    #[default]
+    Wait,
+    SkipLocked,
+    NoWait,
+}
+
+/// `for update [of t] [skip locked | nowait]` and friends of postgres.
+#[derive(Debug, Clone)]
+pub struct RowLock<'a> {
+    pub strength: LockStrength,
+    pub of: Vec<&'a str>,
+    pub wait: LockWait,
+}
+
+impl<'a> RowLock<'a> {
+    pub fn new(strength: LockStrength) -> Self {
+        Self {
+            strength,
+            of: vec![],
+            wait: LockWait::default(),
+        }
+    }
+
+    pub fn for_update() -> Self {
+        Self::new(LockStrength::Update)
+    }
+
+    pub fn for_share() -> Self {
+        Self::new(LockStrength::Share)
+    }
+
+    /// Only locks rows of the tables with these aliases.
+    pub fn of<I: IntoIterator<Item = &'a str>>(mut self, tables: I) -> Self {
+        self.of.extend(tables);
+        self
+    }
+
+    pub fn skip_locked(self) -> Self {
+        Self {
+            wait: LockWait::SkipLocked,
+            ..self
+        }
+    }
+
+    pub fn nowait(self) -> Self {
+        Self {
+            wait: LockWait::NoWait,
+            ..self
+        }
+    }
+
+    fn to_sql(&self, db_type: DbType) -> Result<String, ChinSqlError> {
+        if db_type != DbType::Postgres {
+            return Err(ChinSqlError::BuilderSqlError(format!(
+                "{db_type:?} does not support row locks"
+            )));
+        }
+        let mut seg = String::from(match self.strength {
+            LockStrength::Update => "for update",
+            LockStrength::NoKeyUpdate => "for no key update",
+            LockStrength::Share => "for share",
+            LockStrength::KeyShare => "for key share",
+        });
+        if !self.of.is_empty() {
+            seg.push_str(" of ");
+            seg.push_str(&self.of.join(", "));
+        }
+        match self.wait {
+            LockWait::Wait => {}
+            LockWait::SkipLocked => seg.push_str(" skip locked"),
+            LockWait::NoWait => seg.push_str(" nowait"),
+        }
+        Ok(seg)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum OrderBy<'a> {
     Asc(Cow<'a, str>),
     Desc(Cow<'a, str>),
+    AscNulls(Cow<'a, str>, NullsOrder),
+    DescNulls(Cow<'a, str>, NullsOrder),
+    /// See [`JsonPath::asc`].
+    Json {
+        path: JsonPath<'a>,
+        desc: bool,
+        nulls: Option<NullsOrder>,
+    },
     None,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+impl<'a> OrderBy<'a> {
+    pub fn nulls_first(self) -> Self {
+        self.nulls(NullsOrder::First)
+    }
+
+    pub fn nulls_last(self) -> Self {
+        self.nulls(NullsOrder::Last)
+    }
+
+    fn nulls(self, nulls: NullsOrder) -> Self {
+        match self {
+            OrderBy::Asc(cow) | OrderBy::AscNulls(cow, _) => OrderBy::AscNulls(cow, nulls),
+            OrderBy::Desc(cow) | OrderBy::DescNulls(cow, _) => OrderBy::DescNulls(cow, nulls),
+            OrderBy::Json { path, desc, .. } => OrderBy::Json {
+                path,
+                desc,
+                nulls: Some(nulls),
+            },
+            OrderBy::None => OrderBy::None,
+        }
+    }
+
+    pub(crate) fn to_sql(&self, db_type: DbType) -> Option<String> {
+        let nulls = |n: &NullsOrder| match n {
+            NullsOrder::First => "nulls first",
+            NullsOrder::Last => "nulls last",
+        };
+        match self {
+            OrderBy::Asc(cow) => Some(format!("{} asc", cow)),
+            OrderBy::Desc(cow) => Some(format!("{} desc", cow)),
+            OrderBy::AscNulls(cow, n) => Some(format!("{} asc {}", cow, nulls(n))),
+            OrderBy::DescNulls(cow, n) => Some(format!("{} desc {}", cow, nulls(n))),
+            OrderBy::Json {
+                path,
+                desc,
+                nulls: n,
+            } => {
+                let mut sql = path.to_sql(db_type);
+                sql.push_str(if *desc { " desc" } else { " asc" });
+                if let Some(n) = n {
+                    sql.push(' ');
+                    sql.push_str(nulls(n));
+                }
+                Some(sql)
+            }
+            OrderBy::None => None,
+        }
+    }
+}
+
 impl LimitOffset {
     pub fn new(limit: usize) -> Self {
         Self {
@@ -174,17 +408,33 @@ impl LimitOffset {
 }
 
 impl<'a> CustomSqlSeg<'a> for LimitOffset {
-    fn build(&self, _: &mut PlaceHolderType) -> Option<SqlSeg<'a>> {
-        match self.offset {
-            Some(v) => Some(SqlSeg::of(
-                format!("limit {} offset {}", self.limit, v),
-                vec![],
-            )),
-            None => Some(SqlSeg::of(format!("limit {}", self.limit), vec![])),
-        }
+    fn build(
+        &self,
+        _: DbType,
+        pht: &mut PlaceHolderType,
+    ) -> Result<Option<SqlSeg<'a>>, ChinSqlError> {
+        Ok(Some(limit_seg(
+            SqlValue::I64(self.limit as i64),
+            self.offset.map(|v| SqlValue::I64(v as i64)),
+            pht,
+        )))
     }
 }
 
+fn limit_seg<'a>(
+    limit: SqlValue<'a>,
+    offset: Option<SqlValue<'a>>,
+    pht: &mut PlaceHolderType,
+) -> SqlSeg<'a> {
+    let mut seg = format!("limit {}", pht.next_ph());
+    let mut values = vec![limit];
+    if let Some(offset) = offset {
+        seg.push_str(&format!(" offset {}", pht.next_ph()));
+        values.push(offset);
+    }
+    SqlSeg::of(seg, values)
+}
+
 impl<'a> IntoSqlSeg<'a> for SqlBuilder<'a> {
     fn into_sql_seg2(
         self,
@@ -194,6 +444,13 @@ impl<'a> IntoSqlSeg<'a> for SqlBuilder<'a> {
         if self.segs.is_empty() {
             Err(ChinSqlError::BuilderSqlError("segs is empty".into()))?
         }
+        if let Some((table, _)) = self
+            .reads
+            .iter()
+            .find(|(t, c)| table_columns(t, *c).is_some_and(|c| !c.is_empty()))
+        {
+            return Err(ChinSqlError::MissingTenantScope(table.clone()));
+        }
 
         let mut sb = String::new();
         let mut values: Vec<SqlValue<'a>> = Vec::new();
@@ -201,17 +458,50 @@ impl<'a> IntoSqlSeg<'a> for SqlBuilder<'a> {
         for seg in self.segs {
             match seg {
                 SqlBuilderSeg::Where(wr) => {
-                    if let Some(ss) = wr.build(db_type, pht) {
+                    if let Some(ss) = wr.build(db_type, pht)? {
                         sb.push_str(" where ");
                         sb.push_str(&ss.seg);
                         values.extend(ss.values)
                     }
                 }
+                SqlBuilderSeg::Fields(fields) => {
+                    let fields: Vec<String> = fields.iter().map(|f| f.to_sql(db_type)).collect();
+                    sb.push_str(&fields.join(", "));
+                }
+                SqlBuilderSeg::OrderBy(orders) => {
+                    let orders: Vec<String> =
+                        orders.iter().filter_map(|o| o.to_sql(db_type)).collect();
+                    sb.push_str(&orders.join(", "));
+                }
+                SqlBuilderSeg::Lock(lock) => {
+                    sb.push_str(&lock.to_sql(db_type)?);
+                }
+                SqlBuilderSeg::Error(err) => return Err(err),
+                SqlBuilderSeg::Join(join) => {
+                    let ss = join.into_sql_seg2(db_type, pht)?;
+                    sb.push_str(&ss.seg);
+                    values.extend(ss.values);
+                }
+                SqlBuilderSeg::Clause(keyword, wr) => {
+                    if let Some(ss) = wr.build(db_type, pht)? {
+                        sb.push(' ');
+                        sb.push_str(keyword);
+                        sb.push(' ');
+                        sb.push_str(&ss.seg);
+                        values.extend(ss.values)
+                    }
+                }
+                SqlBuilderSeg::GroupBy(group_by) => {
+                    if let Some(seg) = group_by.to_sql(db_type)? {
+                        sb.push_str(" group by ");
+                        sb.push_str(&seg);
+                    }
+                }
                 SqlBuilderSeg::Comma(vs) => {
                     sb.push_str(vs.join(", ").as_str());
                 }
                 SqlBuilderSeg::Custom(custom) => {
-                    if let Some(cs) = custom.build(pht) {
+                    if let Some(cs) = custom.build(db_type, pht)? {
                         sb.push_str(&cs.seg);
                         values.extend(cs.values)
                     }
@@ -239,11 +529,19 @@ impl<'a> IntoSqlSeg<'a> for SqlBuilder<'a> {
                         values.push(val);
                     }
                 },
+                SqlBuilderSeg::LimitSlots => {
+                    let SqlSeg { seg, values: vs } =
+                        limit_seg(SqlValue::slot("limit"), Some(SqlValue::slot("offset")), pht);
+                    sb.push_str(&seg);
+                    values.extend(vs);
+                }
                 SqlBuilderSeg::LimitOffset(limit_offset) => {
                     let SqlSeg { seg, values: vs } =
-                        limit_offset.build(pht).ok_or(ChinSqlError::TransformError(
-                            "Unable convert limit offset to sql seg.".to_owned(),
-                        ))?;
+                        limit_offset
+                            .build(db_type, pht)?
+                            .ok_or(ChinSqlError::TransformError(
+                                "Unable convert limit offset to sql seg.".to_owned(),
+                            ))?;
                     sb.push_str(&seg);
                     values.extend(vs);
                 }
@@ -259,28 +557,45 @@ impl<'a> IntoSqlSeg<'a> for SqlBuilder<'a> {
 
 impl<'a> From<&'a str> for SqlBuilder<'a> {
     fn from(value: &'a str) -> Self {
-        Self {
-            segs: vec![SqlBuilderSeg::SegOrVal(value.into())],
-        }
+        SqlBuilder::new().seg(value)
     }
 }
 
 pub enum JoinType {
     LeftJoin,
     InnerJoin,
+    /// Sqlite since 3.39.0
     RightJoin,
+    /// Sqlite since 3.39.0
+    FullJoin,
+    CrossJoin,
 }
 
-pub struct JoinCond<'a> {
-    l_table: &'a str,
-    l_field: &'a str,
-    r_table: &'a str,
-    r_field: &'a str,
+impl JoinType {
+    fn keyword(&self) -> &'static str {
+        match self {
+            JoinType::LeftJoin => "left join",
+            JoinType::InnerJoin => "inner join",
+            JoinType::RightJoin => "right join",
+            JoinType::FullJoin => "full outer join",
+            JoinType::CrossJoin => "cross join",
+        }
+    }
+}
+
+pub enum JoinCond<'a> {
+    FieldEq {
+        l_table: &'a str,
+        l_field: &'a str,
+        r_table: &'a str,
+        r_field: &'a str,
+    },
+    Wheres(Wheres<'a>),
 }
 
 impl<'a, T> From<(SqlTypedField<'a, T>, SqlTypedField<'a, T>)> for JoinCond<'a> {
     fn from(value: (SqlTypedField<'a, T>, SqlTypedField<'a, T>)) -> Self {
-        JoinCond {
+        JoinCond::FieldEq {
             l_table: value.0.table_alias,
             l_field: value.0.field_name,
             r_table: value.1.table_alias,
@@ -288,10 +603,135 @@ impl<'a, T> From<(SqlTypedField<'a, T>, SqlTypedField<'a, T>)> for JoinCond<'a>
         }
     }
 }
+
+impl<'a> From<Wheres<'a>> for JoinCond<'a> {
+    fn from(value: Wheres<'a>) -> Self {
+        JoinCond::Wheres(value)
+    }
+}
+
+impl<'a> From<JoinCond<'a>> for Wheres<'a> {
+    fn from(value: JoinCond<'a>) -> Self {
+        match value {
+            JoinCond::FieldEq {
+                l_table,
+                l_field,
+                r_table,
+                r_field,
+            } => Wheres::Raw(format!("{l_table}.{l_field} = {r_table}.{r_field}").into()),
+            JoinCond::Wheres(wheres) => wheres,
+        }
+    }
+}
+
 pub struct JoinTable<'a> {
     pub join_type: JoinType,
     pub table: Froms<'a>,
+    /// joined with `and`
     pub conds: Vec<JoinCond<'a>>,
+    /// `using (...)` instead of `on`
+    pub using: Vec<&'a str>,
+    /// Postgres only, the table is usually a sub query referring to the former tables.
+    pub lateral: bool,
+}
+
+impl<'a> JoinTable<'a> {
+    pub fn new(join_type: JoinType, table: Froms<'a>) -> Self {
+        Self {
+            join_type,
+            table,
+            conds: vec![],
+            using: vec![],
+            lateral: false,
+        }
+    }
+
+    pub fn on<C: Into<JoinCond<'a>>>(mut self, cond: C) -> Self {
+        self.conds.push(cond.into());
+        self
+    }
+
+    pub fn using<I: IntoIterator<Item = &'a str>>(mut self, columns: I) -> Self {
+        self.using.extend(columns);
+        self
+    }
+
+    pub fn lateral(self) -> Self {
+        Self {
+            lateral: true,
+            ..self
+        }
+    }
+
+    fn check(&self, db_type: DbType, sqlite_version: i32) -> Result<(), ChinSqlError> {
+        let err = |msg: &str| Err(ChinSqlError::BuilderSqlError(msg.to_owned()));
+        if !self.conds.is_empty() && !self.using.is_empty() {
+            return err("join can not have both on and using");
+        }
+        match self.join_type {
+            JoinType::CrossJoin if !self.conds.is_empty() || !self.using.is_empty() => {
+                return err("cross join takes no condition");
+            }
+            JoinType::CrossJoin => {}
+            _ if self.conds.is_empty() && self.using.is_empty() => {
+                return err("join condition is empty");
+            }
+            // e.g. `Wheres::None` or an empty `and`, which the join would silently drop
+            _ if self
+                .conds
+                .iter()
+                .any(|cond| matches!(cond, JoinCond::Wheres(wheres) if wheres.empty())) =>
+            {
+                return err("join condition is empty");
+            }
+            JoinType::RightJoin | JoinType::FullJoin
+                if db_type == DbType::Sqlite && sqlite_version < 3_039_000 =>
+            {
+                return err("right and full join need sqlite 3.39.0");
+            }
+            _ => {}
+        }
+        if self.lateral && db_type == DbType::Sqlite {
+            return err("sqlite does not support lateral join");
+        }
+        Ok(())
+    }
+}
+
+// the linked library decides which syntax sqlite understands
+#[cfg(feature = "sqlite")]
+pub(crate) fn sqlite_version() -> i32 {
+    rusqlite::version_number()
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub(crate) fn sqlite_version() -> i32 {
+    i32::MAX
+}
+
+impl<'a> IntoSqlSeg<'a> for JoinTable<'a> {
+    fn into_sql_seg2(
+        self,
+        db_type: DbType,
+        pht: &mut PlaceHolderType,
+    ) -> Result<SqlSeg<'a>, ChinSqlError> {
+        self.check(db_type, sqlite_version())?;
+
+        let mut sb = SqlBuilder::new().seg(self.join_type.keyword());
+        if self.lateral {
+            sb = sb.seg("lateral");
+        }
+        sb = sb.merge(self.table);
+        if !self.using.is_empty() {
+            sb = sb.seg(format!("using ({})", self.using.join(", ")));
+        } else if !self.conds.is_empty() {
+            sb.segs.push(SqlBuilderSeg::Clause(
+                "on",
+                Wheres::and(self.conds.into_iter().map(Wheres::from).collect::<Vec<_>>()),
+            ));
+        }
+        sb.into_sql_seg2(db_type, pht)
+    }
 }
 
 pub struct Joins<'a> {
@@ -334,41 +774,9 @@ impl<'a> From<Joins<'a>> for SqlBuilder<'a> {
     fn from(value: Joins<'a>) -> Self {
         let mut sql_builder = SqlBuilder::new();
         sql_builder = sql_builder.merge(value.base);
-        for table in value.joins {
-            let JoinTable {
-                join_type,
-                table,
-                conds,
-            } = table;
-
-            match join_type {
-                JoinType::LeftJoin => {
-                    sql_builder = sql_builder.seg("left join");
-                }
-                JoinType::InnerJoin => {
-                    sql_builder = sql_builder.seg("inner join");
-                }
-                JoinType::RightJoin => {
-                    sql_builder = sql_builder.seg("right join");
-                }
-            };
-            sql_builder = sql_builder.merge(table).seg("on");
-            let cond_len = conds.len();
-            for (i, join_cond) in conds.into_iter().enumerate() {
-                sql_builder = sql_builder
-                    .seg(join_cond.l_table)
-                    .seg(".")
-                    .seg(join_cond.l_field)
-                    .seg("=")
-                    .seg(join_cond.r_table)
-                    .seg(".")
-                    .seg(join_cond.r_field);
-                if i < cond_len - 1 {
-                    sql_builder = sql_builder.seg("and");
-                }
-            }
-        }
-
+        sql_builder
+            .segs
+            .extend(value.joins.into_iter().map(SqlBuilderSeg::Join));
         sql_builder
     }
 }
@@ -436,17 +844,99 @@ impl<'a> From<Froms<'a>> for SqlBuilder<'a> {
 #[derive(Debug, Default)]
 pub enum GroupBy<'a> {
     Plain(Vec<Cow<'a, str>>),
+    /// Postgres and duckdb only
+    Rollup(Vec<Cow<'a, str>>),
+    /// Postgres and duckdb only
+    Cube(Vec<Cow<'a, str>>),
+    /// Postgres and duckdb only, an empty set is the grand total.
+    GroupingSets(Vec<Vec<Cow<'a, str>>>),
     #[default]
     None,
 }
 
+impl<'a> GroupBy<'a> {
+    pub fn plain<I: IntoIterator<Item = S>, S: Into<Cow<'a, str>>>(fields: I) -> Self {
+        Self::Plain(fields.into_iter().map(Into::into).collect())
+    }
+
+    pub fn rollup<I: IntoIterator<Item = S>, S: Into<Cow<'a, str>>>(fields: I) -> Self {
+        Self::Rollup(fields.into_iter().map(Into::into).collect())
+    }
+
+    pub fn cube<I: IntoIterator<Item = S>, S: Into<Cow<'a, str>>>(fields: I) -> Self {
+        Self::Cube(fields.into_iter().map(Into::into).collect())
+    }
+
+    pub fn grouping_sets<I, G, S>(sets: I) -> Self
+    where
+        I: IntoIterator<Item = G>,
+        G: IntoIterator<Item = S>,
+        S: Into<Cow<'a, str>>,
+    {
+        Self::GroupingSets(
+            sets.into_iter()
+                .map(|set| set.into_iter().map(Into::into).collect())
+                .collect(),
+        )
+    }
+
+    fn to_sql(&self, db_type: DbType) -> Result<Option<String>, ChinSqlError> {
+        let seg = match self {
+            GroupBy::Plain(fields) if !fields.is_empty() => fields.join(", "),
+            GroupBy::Plain(_) | GroupBy::None => return Ok(None),
+            _ if db_type == DbType::Sqlite => {
+                return Err(ChinSqlError::BuilderSqlError(format!(
+                    "{self:?} is not supported by {db_type:?}"
+                )));
+            }
+            GroupBy::Rollup(fields) => format!("rollup ({})", fields.join(", ")),
+            GroupBy::Cube(fields) => format!("cube ({})", fields.join(", ")),
+            GroupBy::GroupingSets(sets) => {
+                let sets: Vec<String> = sets
+                    .iter()
+                    .map(|set| format!("({})", set.join(", ")))
+                    .collect();
+                format!("grouping sets ({})", sets.join(", "))
+            }
+        };
+        Ok(Some(seg))
+    }
+}
+
+impl<'a> From<Vec<SqlField<'a>>> for GroupBy<'a> {
+    fn from(fields: Vec<SqlField<'a>>) -> Self {
+        Self::plain(
+            fields
+                .iter()
+                .map(|f| format!("{}.{}", f.table_alias, f.field_name)),
+        )
+    }
+}
+
 #[derive(Debug, Default)]
 pub enum Having<'a> {
     Custom(Cow<'a, str>),
+    Wheres(Wheres<'a>),
     #[default]
     None,
 }
 
+impl<'a> From<Wheres<'a>> for Having<'a> {
+    fn from(value: Wheres<'a>) -> Self {
+        Self::Wheres(value)
+    }
+}
+
+impl<'a> From<Having<'a>> for Wheres<'a> {
+    fn from(value: Having<'a>) -> Self {
+        match value {
+            Having::Custom(cow) => Wheres::Raw(cow),
+            Having::Wheres(wheres) => wheres,
+            Having::None => Wheres::None,
+        }
+    }
+}
+
 pub struct SqlReader<'a> {
     fields: Vec<SqlField<'a>>,
     froms: Froms<'a>,
@@ -455,9 +945,16 @@ pub struct SqlReader<'a> {
     having: Having<'a>,
     order_by: Option<Vec<OrderBy<'a>>>,
     limit: Option<LimitOffset>,
+    limit_slots: bool,
+    lock: Option<RowLock<'a>>,
+    tenant: Option<TenantScope<'a>>,
 }
 
 impl<'a> SqlReader<'a> {
+    pub(crate) fn froms(&self) -> &Froms<'a> {
+        &self.froms
+    }
+
     pub fn builder<V: Into<Vec<SqlField<'a>>>>(
         fields: V,
         froms: Froms<'a>,
@@ -469,9 +966,13 @@ impl<'a> SqlReader<'a> {
                 wheres: Wheres::None,
                 order_by: None,
                 limit: None,
+                limit_slots: false,
+                lock: None,
                 group_by: Default::default(),
                 having: Default::default(),
+                tenant: None,
             },
+            projection: PhantomData,
         }
     }
 
@@ -479,13 +980,38 @@ impl<'a> SqlReader<'a> {
         self.limit.replace(limit);
         self
     }
+
+    pub fn fields(&self) -> &[SqlField<'a>] {
+        &self.fields
+    }
+
+    /// Scopes every tenant table of the reader and its sub queries, see [`TenantScope`].
+    pub fn scope(mut self, scope: &TenantScope<'a>) -> Self {
+        self.tenant = Some(scope.clone());
+        self
+    }
+
+    pub(crate) fn inherit_scope(&mut self, scope: Option<&TenantScope<'a>>) {
+        if self.tenant.is_none() {
+            self.tenant = scope.cloned();
+        }
+    }
+
+    fn apply_scope(&mut self) -> Result<(), ChinSqlError> {
+        let mut scoped = vec![];
+        scope_froms(&mut self.froms, self.tenant.as_ref(), &mut scoped)?;
+        let wheres = std::mem::replace(&mut self.wheres, Wheres::None);
+        self.wheres = and_scoped(wheres, scoped);
+        Ok(())
+    }
 }
 
-pub struct SqlReaderBuilder<'a> {
-    reader: SqlReader<'a>,
+pub struct SqlReaderBuilder<'a, P = ()> {
+    pub(crate) reader: SqlReader<'a>,
+    pub(crate) projection: PhantomData<fn() -> P>,
 }
 
-impl<'a> SqlReaderBuilder<'a> {
+impl<'a, P> SqlReaderBuilder<'a, P> {
     pub fn wheres(mut self, wheres: Wheres<'a>) -> Self {
         self.reader.wheres = wheres;
         self
@@ -501,6 +1027,18 @@ impl<'a> SqlReaderBuilder<'a> {
         self
     }
 
+    /// `limit` and `offset` as the slots of the same names of a [`crate::SqlTemplate`].
+    pub fn limit_slots(mut self) -> Self {
+        self.reader.limit_slots = true;
+        self
+    }
+
+    /// Postgres only, building for sqlite fails.
+    pub fn lock(mut self, lock: RowLock<'a>) -> Self {
+        self.reader.lock.replace(lock);
+        self
+    }
+
     pub fn group_by<T: Into<GroupBy<'a>>>(mut self, group_by: T) -> Self {
         self.reader.group_by = group_by.into();
         self
@@ -511,60 +1049,49 @@ impl<'a> SqlReaderBuilder<'a> {
         self
     }
 
+    pub fn scope(mut self, scope: &TenantScope<'a>) -> Self {
+        self.reader.tenant = Some(scope.clone());
+        self
+    }
+
     pub fn build(self) -> SqlReader<'a> {
         self.reader
     }
 }
 
 impl<'a> From<SqlReader<'a>> for SqlBuilder<'a> {
-    fn from(value: SqlReader<'a>) -> Self {
-        let select = value
-            .fields
-            .iter()
-            .map(|m| match m.alias {
-                Some(alias) => {
-                    format!("{}.{} as {}", m.table_alias, m.field_name, alias)
-                }
-                None => {
-                    format!("{}.{}", m.table_alias, m.field_name)
-                }
-            })
-            .collect::<Vec<String>>()
-            .join(", ");
+    fn from(mut value: SqlReader<'a>) -> Self {
+        if let Err(err) = value.apply_scope() {
+            let mut sb = SqlBuilder::new();
+            sb.segs.push(SqlBuilderSeg::Error(err));
+            return sb;
+        }
         SqlBuilder::new()
             .seg("select")
-            .seg(select)
+            .fields(value.fields)
             .seg("from")
             .merge(value.froms)
             .r#where(value.wheres)
-            .transform(|this| match value.group_by {
-                GroupBy::Plain(cows) => this.seg("order by").seg(cows.join(", ")),
-                GroupBy::None => this,
-            })
-            .transform(|this| match value.having {
-                Having::Custom(cow) => this.seg("having").seg(cow),
-                Having::None => this,
+            .group_by(value.group_by)
+            .having(value.having)
+            .transform(|mut this| match value.order_by {
+                Some(order_by) if order_by.iter().any(|ob| !matches!(ob, OrderBy::None)) => {
+                    this = this.seg("order by");
+                    this.segs.push(SqlBuilderSeg::OrderBy(order_by));
+                    this
+                }
+                _ => this,
             })
-            .transform(|this| match value.order_by {
-                Some(order_by) => {
-                    let c: Vec<String> = order_by
-                        .iter()
-                        .filter_map(|ob| match ob {
-                            OrderBy::Asc(cow) => Some(format!("{} asc", cow)),
-                            OrderBy::Desc(cow) => Some(format!("{} desc", cow)),
-                            OrderBy::None => None,
-                        })
-                        .collect();
-                    if !c.is_empty() {
-                        this.seg("order by").seg(c.join(", "))
-                    } else {
-                        this
-                    }
+            .transform(|mut this| match (value.limit_slots, value.limit) {
+                (true, _) => {
+                    this.segs.push(SqlBuilderSeg::LimitSlots);
+                    this
                 }
-                None => this,
+                (false, Some(lo)) => this.limit_offset(lo),
+                (false, None) => this,
             })
-            .transform(|this| match value.limit {
-                Some(lo) => this.limit_offset(lo),
+            .transform(|this| match value.lock {
+                Some(lock) => this.lock(lock),
                 None => this,
             })
     }
@@ -584,3 +1111,164 @@ impl<'a> IntoSqlSeg<'a> for SqlReader<'a> {
 pub struct SubQueryTable<'a> {
     pub reader: SqlReader<'a>,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DbType, Froms, GroupBy, IntoSqlSeg, JoinTable, JoinType, Joins, SqlReader, SqlTypedField,
+        Wheres,
+    };
+
+    #[test]
+    fn group_by_having() {
+        let dept = SqlTypedField::<i64>::new("e", "dept");
+        let age = SqlTypedField::<i64>::new("e", "age");
+        let reader = |group_by: GroupBy<'static>| {
+            SqlReader::builder(
+                vec![dept.erased()],
+                Froms::Table {
+                    table_name: "employee",
+                    alias: "e",
+                },
+            )
+            .wheres(age.v_gt(18))
+            .group_by(group_by)
+            .having(Wheres::compare("count(*)", ">", 2))
+            .build()
+        };
+
+        let seg = reader(vec![dept.erased()].into())
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            "select e.dept from employee as e  where e.age > $1  group by e.dept  having count(*) > $2 ",
+            seg.seg
+        );
+        assert_eq!(2, seg.values.len());
+
+        let seg = reader(GroupBy::grouping_sets([vec![dept.twn()], vec![]]))
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert!(seg.seg.contains("group by grouping sets ((e.dept), ())"));
+
+        assert!(
+            reader(GroupBy::rollup([dept.twn()]))
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn join_conditions() {
+        let table = |table_name, alias| Froms::Table { table_name, alias };
+        let o_id = SqlTypedField::<i64>::new("o", "id");
+        let o_at = SqlTypedField::<i64>::new("o", "created_at");
+        let p_order = SqlTypedField::<i64>::new("p", "order_id");
+        let r_from = SqlTypedField::<i64>::new("r", "valid_from");
+        let joins = || {
+            Joins::new(table("orders", "o"))
+                .join(
+                    JoinTable::new(JoinType::LeftJoin, table("payment", "p"))
+                        .on((o_id.clone(), p_order.clone()))
+                        .on(Wheres::or([
+                            Wheres::equal("p.state", "paid"),
+                            Wheres::equal("p.state", "refund"),
+                        ])),
+                )
+                .join(
+                    JoinTable::new(JoinType::FullJoin, table("rate", "r")).on(Wheres::SOV(vec![
+                        crate::SegOrVal::Str(r_from.twn()),
+                        " <= ".into(),
+                        crate::SegOrVal::Str(o_at.twn()),
+                        " + ".into(),
+                        crate::SegOrVal::val(60),
+                    ])),
+                )
+                .join(JoinTable::new(JoinType::CrossJoin, table("region", "g")))
+                .join(JoinTable::new(JoinType::InnerJoin, table("item", "i")).using(["sku"]))
+        };
+        let reader = SqlReader::builder(vec![o_id.erased()], joins().into())
+            .wheres(o_id.v_gt(1))
+            .build();
+        let seg = reader.into_sql_seg(DbType::Postgres).unwrap();
+        assert_eq!(
+            "select o.id from orders as o left join payment as p  on  o.id = p.order_id  and (p.state = $1 or p.state = $2) full outer join rate as r  on r.valid_from <= o.created_at + $3 cross join region as g inner join item as i using (sku)  where o.id > $4 ",
+            seg.seg
+        );
+        assert_eq!(4, seg.values.len());
+
+        #[cfg(feature = "sqlite")]
+        {
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            for ddl in [
+                "create table orders (id integer, created_at integer, sku text)",
+                "create table payment (order_id integer, state text)",
+                "create table rate (valid_from integer)",
+                "create table region (name text)",
+                "create table item (sku text)",
+            ] {
+                conn.execute(ddl, []).unwrap();
+            }
+            let seg = SqlReader::builder(vec![o_id.erased()], joins().into())
+                .build()
+                .into_sql_seg(DbType::Sqlite)
+                .unwrap();
+            conn.prepare(&seg.seg).unwrap();
+        }
+
+        let lateral = JoinTable::new(JoinType::CrossJoin, table("t", "t")).lateral();
+        assert!(lateral.into_sql_seg(DbType::Sqlite).is_err());
+        let right = JoinTable::new(JoinType::RightJoin, table("t", "t")).using(["id"]);
+        assert!(right.check(DbType::Sqlite, 3_038_005).is_err());
+        assert!(right.check(DbType::Sqlite, 3_039_000).is_ok());
+        assert!(
+            JoinTable::new(JoinType::InnerJoin, table("t", "t"))
+                .into_sql_seg(DbType::Postgres)
+                .is_err()
+        );
+        for cond in [Wheres::None, Wheres::and([])] {
+            assert!(
+                JoinTable::new(JoinType::InnerJoin, table("t", "t"))
+                    .on(cond)
+                    .into_sql_seg(DbType::Postgres)
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn row_lock() {
+        let id = SqlTypedField::<i64>::new("j", "id");
+        let state = SqlTypedField::<i64>::new("j", "state");
+        let reader = |lock| {
+            SqlReader::builder(
+                vec![id.erased()],
+                Froms::Table {
+                    table_name: "job",
+                    alias: "j",
+                },
+            )
+            .wheres(state.v_eq(0))
+            .order_by([id.asc()])
+            .limit(crate::LimitOffset::new(10))
+            .lock(lock)
+            .build()
+        };
+        let seg = reader(crate::RowLock::for_update().of(["j"]).skip_locked())
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert!(
+            seg.seg
+                .ends_with("order by j.id asc limit $2 for update of j skip locked ")
+        );
+        let seg = reader(crate::RowLock::for_share().nowait())
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert!(seg.seg.ends_with("limit $2 for share nowait "));
+        assert!(
+            reader(crate::RowLock::for_update())
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+    }
+}
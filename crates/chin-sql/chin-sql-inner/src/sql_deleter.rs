@@ -1,17 +1,30 @@
-use crate::{ChinSqlError, DbType, IntoSqlSeg};
+use crate::{
+    ChinSqlError, DbType, Froms, IntoSqlSeg, OrderBy, SqlBuilder, TenantScope,
+    tenant::{and_scoped, scope_froms, scope_table},
+};
 
 use super::{SqlSeg, place_hoder::PlaceHolderType, sql_value::SqlValue, wheres::Wheres};
 
 pub struct SqlDeleter<'a> {
     table: &'a str,
+    using: Option<Froms<'a>>,
     wheres: Wheres<'a>,
+    order_by: Vec<OrderBy<'a>>,
+    limit: Option<usize>,
+    tenant: Option<TenantScope<'a>>,
+    tenant_columns: Option<&'static [&'static str]>,
 }
 
 impl<'a> SqlDeleter<'a> {
     pub fn new(table: &'a str) -> Self {
         SqlDeleter {
             table,
+            using: None,
             wheres: Wheres::and([]),
+            order_by: vec![],
+            limit: None,
+            tenant: None,
+            tenant_columns: None,
         }
     }
 
@@ -19,21 +32,118 @@ impl<'a> SqlDeleter<'a> {
         self.wheres = wheres;
         self
     }
+
+    /// `delete from t using other` on postgres, a `rowid in (select ...)` on sqlite, which
+    /// has the same table restrictions as [`SqlDeleter::limit`].
+    pub fn using<F: Into<Froms<'a>>>(mut self, using: F) -> Self {
+        self.using = Some(using.into());
+        self
+    }
+
+    pub fn order_by<T: Into<Vec<OrderBy<'a>>>>(mut self, orders: T) -> Self {
+        self.order_by = orders.into();
+        self
+    }
+
+    /// Deletes at most `limit` rows, the first ones by [`SqlDeleter::order_by`], a limit of 0
+    /// fails to build.
+    ///
+    /// The rows are picked by `rowid` / `ctid` in a sub query, so neither sqlite `without rowid`
+    /// tables nor postgres partitioned tables can be limited.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Scopes the table and the tables of [`SqlDeleter::using`], see [`TenantScope`].
+    pub fn scope(mut self, scope: &TenantScope<'a>) -> Self {
+        self.tenant = Some(scope.clone());
+        self
+    }
+
+    /// Marks the table as tenant scoped by `columns` without [`crate::register_tenant_table`],
+    /// e.g. the `TENANT_COLUMNS` of a derived table.
+    pub fn tenant_columns(mut self, columns: &'static [&'static str]) -> Self {
+        self.tenant_columns = Some(columns);
+        self
+    }
+
+    pub(crate) fn batch_limit(&self) -> Result<usize, ChinSqlError> {
+        match self.limit {
+            Some(0) => Err(zero_limit()),
+            Some(limit) => Ok(limit),
+            None => Err(ChinSqlError::BuilderSqlError(
+                "batch delete needs a limit".to_owned(),
+            )),
+        }
+    }
+}
+
+fn zero_limit() -> ChinSqlError {
+    ChinSqlError::BuilderSqlError("delete limit must be greater than 0".to_owned())
 }
 
 impl<'a> IntoSqlSeg<'a> for SqlDeleter<'a> {
     fn into_sql_seg2(
-        self,
+        mut self,
         db_type: DbType,
         pht: &mut PlaceHolderType,
     ) -> Result<SqlSeg<'a>, ChinSqlError> {
+        // the scope alone must not turn a delete without filter into deleting all rows
+        if self.wheres.empty() {
+            return Err(ChinSqlError::FilterBuildError(
+                "filter_is_empty".to_string(),
+            ));
+        }
+        if self.limit == Some(0) {
+            return Err(zero_limit());
+        }
+        let scope = self.tenant.as_ref();
+        let mut scoped = vec![scope_table(
+            scope,
+            self.table,
+            self.tenant_columns,
+            Some(self.table),
+        )?];
+        if let Some(using) = self.using.as_mut() {
+            scope_froms(using, scope, &mut scoped)?;
+        }
+        let wheres = and_scoped(self.wheres, scoped);
+
         let mut sb = String::new();
         let mut values: Vec<SqlValue<'a>> = Vec::new();
 
         sb.push_str("delete from ");
         sb.push_str(self.table);
 
-        if let Some(filters) = self.wheres.build(db_type, pht) {
+        let nested = self.limit.is_some()
+            || !self.order_by.is_empty()
+            || (self.using.is_some() && db_type == DbType::Sqlite);
+
+        if nested {
+            // no database can limit a delete, the rows are picked by a sub query
+            let key = match db_type {
+                DbType::Sqlite | DbType::DuckDb => "rowid",
+                DbType::Postgres => "ctid",
+            };
+            let table = self.table;
+            sb.push_str(&format!(
+                " where {key} in (select {table}.{key} from {table}"
+            ));
+            if let Some(using) = self.using {
+                let using = SqlBuilder::from(using).into_sql_seg2(db_type, pht)?;
+                sb.push_str(", ");
+                sb.push_str(&using.seg);
+                values.extend(using.values);
+            }
+        } else if let Some(using) = self.using {
+            let using = SqlBuilder::from(using).into_sql_seg2(db_type, pht)?;
+            sb.push_str(" using ");
+            sb.push_str(&using.seg);
+            values.extend(using.values);
+        }
+
+        if let Some(filters) = wheres.build(db_type, pht)? {
             sb.push_str(" where ");
             sb.push_str(filters.seg.as_str());
 
@@ -44,6 +154,96 @@ impl<'a> IntoSqlSeg<'a> for SqlDeleter<'a> {
             ))?
         }
 
+        if nested {
+            let orders: Vec<String> = self
+                .order_by
+                .iter()
+                .filter_map(|o| o.to_sql(db_type))
+                .collect();
+            if !orders.is_empty() {
+                sb.push_str(" order by ");
+                sb.push_str(&orders.join(", "));
+            }
+            if let Some(limit) = self.limit {
+                sb.push_str(" limit ");
+                sb.push_str(&pht.next_ph());
+                values.push(SqlValue::I64(limit as i64));
+            }
+            sb.push(')');
+        }
+
         Ok(SqlSeg::of(sb, values))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{DbType, Froms, IntoSqlSeg, OrderBy, SqlDeleter, Wheres};
+
+    #[test]
+    fn using_and_limit() {
+        let using = || {
+            SqlDeleter::new("event")
+                .using(Froms::Table {
+                    table_name: "device",
+                    alias: "d",
+                })
+                .r#where(Wheres::and([
+                    Wheres::Raw("event.device_id = d.id".into()),
+                    Wheres::equal("d.retired", true),
+                ]))
+        };
+        assert_eq!(
+            "delete from event using device as d  where  event.device_id = d.id  and d.retired = $1",
+            using().into_sql_seg(DbType::Postgres).unwrap().seg
+        );
+
+        let batch = || {
+            SqlDeleter::new("event")
+                .r#where(Wheres::compare("ts", "<", 100))
+                .order_by([OrderBy::Asc("ts".into())])
+                .limit(2)
+        };
+        let seg = batch().into_sql_seg(DbType::Postgres).unwrap();
+        assert_eq!(
+            "delete from event where ctid in (select event.ctid from event where ts < $1 order by ts asc limit $2)",
+            seg.seg
+        );
+
+        assert!(
+            SqlDeleter::new("event")
+                .limit(1)
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+        let zero = || {
+            SqlDeleter::new("event")
+                .r#where(Wheres::compare("ts", "<", 100))
+                .limit(0)
+        };
+        assert!(zero().into_sql_seg(DbType::Sqlite).is_err());
+        assert!(zero().batch_limit().is_err());
+
+        #[cfg(feature = "sqlite")]
+        {
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            conn.execute_batch(
+                "create table device (id integer, retired bool);
+                create table event (device_id integer, ts integer);
+                insert into device values (1, true), (2, false);
+                insert into event values (1, 10), (1, 20), (2, 30), (2, 40), (2, 50);",
+            )
+            .unwrap();
+            let execute = |seg: crate::SqlSeg| {
+                conn.execute(&seg.seg, rusqlite::params_from_iter(seg.values))
+                    .unwrap()
+            };
+            assert_eq!(2, execute(using().into_sql_seg(DbType::Sqlite).unwrap()));
+            assert_eq!(2, execute(batch().into_sql_seg(DbType::Sqlite).unwrap()));
+            let left: i64 = conn
+                .query_row("select ts from event", [], |r| r.get(0))
+                .unwrap();
+            assert_eq!(50, left);
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{ChinSqlError, DbType, IntoSqlSeg, PlaceHolderType};
+use crate::{
+    ChinSqlError, DbType, IntoSqlSeg, PlaceHolderType, SqlReader, TenantScope,
+    tenant::{check_value, scope_values},
+};
 
 use super::{SqlSeg, sql_value::SqlValue};
 
@@ -7,6 +10,10 @@ pub struct SqlInserter<'a> {
     fields: Vec<(&'a str, SqlValue<'a>)>,
     extra: Vec<(&'a str, SqlValue<'a>)>,
     on_conflict: OnConflict,
+    source: Option<(Vec<&'a str>, SqlReader<'a>)>,
+    rows: Option<(Vec<&'a str>, Vec<Vec<SqlValue<'a>>>)>,
+    tenant: Option<TenantScope<'a>>,
+    tenant_columns: Option<&'static [&'static str]>,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -24,6 +31,48 @@ impl<'a> SqlInserter<'a> {
             fields: vec![],
             extra: vec![],
             on_conflict: OnConflict::default(),
+            source: None,
+            rows: None,
+            tenant: None,
+            tenant_columns: None,
+        }
+    }
+
+    /// `insert into table (columns) select ...`, the select fields must match the columns.
+    /// Building fails if [`SqlInserter::field`] or [`SqlInserter::raw`] is set as well.
+    pub fn select<C, R>(table: &'a str, columns: C, source: R) -> Self
+    where
+        C: Into<Vec<&'a str>>,
+        R: Into<SqlReader<'a>>,
+    {
+        SqlInserter {
+            table,
+            fields: vec![],
+            extra: vec![],
+            on_conflict: OnConflict::default(),
+            source: Some((columns.into(), source.into())),
+            rows: None,
+            tenant: None,
+            tenant_columns: None,
+        }
+    }
+
+    /// `insert into table (columns) values (..), (..)`, every row must match the columns,
+    /// like [`SqlInserter::select`] it can not be combined with [`SqlInserter::field`].
+    pub fn rows<C, R>(table: &'a str, columns: C, rows: R) -> Self
+    where
+        C: Into<Vec<&'a str>>,
+        R: Into<Vec<Vec<SqlValue<'a>>>>,
+    {
+        SqlInserter {
+            table,
+            fields: vec![],
+            extra: vec![],
+            on_conflict: OnConflict::default(),
+            source: None,
+            rows: Some((columns.into(), rows.into())),
+            tenant: None,
+            tenant_columns: None,
         }
     }
 
@@ -50,14 +99,69 @@ impl<'a> SqlInserter<'a> {
         self.on_conflict = on_conflict;
         self
     }
+
+    /// Sets the tenant columns of the table to the values of the scope, explicit values must
+    /// match it. The source of [`SqlInserter::select`] is scoped and must select them.
+    pub fn scope(mut self, scope: &TenantScope<'a>) -> Self {
+        self.tenant = Some(scope.clone());
+        self
+    }
+
+    /// Marks the table as tenant scoped by `columns` without [`crate::register_tenant_table`],
+    /// e.g. the `TENANT_COLUMNS` of a derived table.
+    pub fn tenant_columns(mut self, columns: &'static [&'static str]) -> Self {
+        self.tenant_columns = Some(columns);
+        self
+    }
 }
 
 impl<'a> IntoSqlSeg<'a> for SqlInserter<'a> {
     fn into_sql_seg2(
-        self,
+        mut self,
         db_type: DbType,
         pht: &mut PlaceHolderType,
     ) -> Result<SqlSeg<'a>, ChinSqlError> {
+        let tenant = scope_values(self.tenant.as_ref(), self.table, self.tenant_columns)?;
+        if (self.source.is_some() || self.rows.is_some())
+            && !(self.fields.is_empty() && self.extra.is_empty())
+        {
+            return Err(ChinSqlError::BuilderSqlError(format!(
+                "insert into {} takes its columns from select or rows, not field",
+                self.table
+            )));
+        }
+        if let Some((columns, mut source)) = self.source {
+            if let Some((column, _)) = tenant.iter().find(|(c, _)| !columns.contains(c)) {
+                return Err(ChinSqlError::BuilderSqlError(format!(
+                    "insert into {} does not select the tenant column {column}",
+                    self.table
+                )));
+            }
+            source.inherit_scope(self.tenant.as_ref());
+            return insert_select(self.table, columns, source, self.on_conflict, db_type, pht);
+        }
+        if let Some((mut columns, mut rows)) = self.rows {
+            for (column, value) in tenant {
+                match columns.iter().position(|c| *c == column) {
+                    Some(i) => {
+                        for v in rows.iter().filter_map(|row| row.get(i)) {
+                            check_value(self.table, column, v, &value)?;
+                        }
+                    }
+                    None => {
+                        columns.push(column);
+                        rows.iter_mut().for_each(|row| row.push(value.clone()));
+                    }
+                }
+            }
+            return insert_rows(self.table, columns, rows, self.on_conflict, db_type, pht);
+        }
+        for (column, value) in tenant {
+            match self.fields.iter().find(|(key, _)| *key == column) {
+                Some((_, v)) => check_value(self.table, column, v, &value)?,
+                None => self.fields.push((column, value)),
+            }
+        }
         if self.fields.is_empty() {
             return Err(ChinSqlError::BuilderSqlError(
                 "insert files is empty".to_owned(),
@@ -93,7 +197,7 @@ impl<'a> IntoSqlSeg<'a> for SqlInserter<'a> {
 
         sql.push_str(pht_vec.join(", ").as_str());
         sql.push(')');
-        if matches!(db_type, DbType::Postgres) {
+        if matches!(db_type, DbType::Postgres | DbType::DuckDb) {
             match self.on_conflict {
                 OnConflict::Ignore => sql.push_str(" ON CONFLICT DO NOTHING"),
                 OnConflict::Replace(cond) => {
@@ -121,3 +225,194 @@ impl<'a> IntoSqlSeg<'a> for SqlInserter<'a> {
         Ok(SqlSeg::of(sql, values))
     }
 }
+
+fn push_conflict_prefix(sql: &mut String, db_type: DbType, on_conflict: &OnConflict) {
+    if matches!(db_type, DbType::Sqlite) {
+        match on_conflict {
+            OnConflict::Ignore => sql.push_str(" or ignore "),
+            OnConflict::Replace(_) => sql.push_str(" or replace "),
+            OnConflict::Default => {}
+        }
+    }
+}
+
+// the conflicting row takes the values of the row which was not inserted
+fn push_conflict_excluded(
+    sql: &mut String,
+    db_type: DbType,
+    on_conflict: OnConflict,
+    columns: &[&str],
+) {
+    if matches!(db_type, DbType::Postgres | DbType::DuckDb) {
+        match on_conflict {
+            OnConflict::Ignore => sql.push_str(" ON CONFLICT DO NOTHING"),
+            OnConflict::Replace(cond) => {
+                sql.push_str(" ON CONFLICT (");
+                sql.push_str(&cond);
+                sql.push_str(") DO UPDATE SET ");
+                let sets: Vec<String> = columns
+                    .iter()
+                    .map(|c| format!("{c} = excluded.{c}"))
+                    .collect();
+                sql.push_str(&sets.join(", "));
+            }
+            OnConflict::Default => {}
+        }
+    }
+}
+
+fn insert_rows<'a>(
+    table: &'a str,
+    columns: Vec<&'a str>,
+    rows: Vec<Vec<SqlValue<'a>>>,
+    on_conflict: OnConflict,
+    db_type: DbType,
+    pht: &mut PlaceHolderType,
+) -> Result<SqlSeg<'a>, ChinSqlError> {
+    if columns.is_empty() || rows.is_empty() {
+        return Err(ChinSqlError::BuilderSqlError(
+            "insert columns or rows is empty".to_owned(),
+        ));
+    }
+    if let Some(row) = rows.iter().find(|row| row.len() != columns.len()) {
+        return Err(ChinSqlError::BuilderSqlError(format!(
+            "insert {} columns from a row of {} values",
+            columns.len(),
+            row.len()
+        )));
+    }
+
+    let mut sql = String::from("insert ");
+    push_conflict_prefix(&mut sql, db_type, &on_conflict);
+    sql.push_str(" into ");
+    sql.push_str(table);
+    sql.push('(');
+    sql.push_str(&columns.join(","));
+    sql.push_str(") values ");
+
+    let mut values = Vec::with_capacity(rows.len() * columns.len());
+    let mut tuples = Vec::with_capacity(rows.len());
+    for row in rows {
+        let phs: Vec<String> = row.iter().map(|_| pht.next_ph()).collect();
+        tuples.push(format!("({})", phs.join(", ")));
+        values.extend(row);
+    }
+    sql.push_str(&tuples.join(", "));
+
+    push_conflict_excluded(&mut sql, db_type, on_conflict, &columns);
+
+    Ok(SqlSeg::of(sql, values))
+}
+
+fn insert_select<'a>(
+    table: &'a str,
+    columns: Vec<&'a str>,
+    source: SqlReader<'a>,
+    on_conflict: OnConflict,
+    db_type: DbType,
+    pht: &mut PlaceHolderType,
+) -> Result<SqlSeg<'a>, ChinSqlError> {
+    if columns.is_empty() {
+        return Err(ChinSqlError::BuilderSqlError(
+            "insert columns is empty".to_owned(),
+        ));
+    }
+    if columns.len() != source.fields().len() {
+        return Err(ChinSqlError::BuilderSqlError(format!(
+            "insert {} columns from {} select fields",
+            columns.len(),
+            source.fields().len()
+        )));
+    }
+
+    let mut sql = String::from("insert ");
+    push_conflict_prefix(&mut sql, db_type, &on_conflict);
+    sql.push_str(" into ");
+    sql.push_str(table);
+    sql.push('(');
+    sql.push_str(&columns.join(","));
+    sql.push_str(") ");
+
+    let select = source.into_sql_seg2(db_type, pht)?;
+    sql.push_str(&select.seg);
+
+    push_conflict_excluded(&mut sql, db_type, on_conflict, &columns);
+
+    Ok(SqlSeg::of(sql, select.values))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DbType, Froms, IntoSqlSeg, OnConflict, SqlInserter, SqlReader, SqlTypedField};
+
+    #[test]
+    fn insert_select() {
+        let id = SqlTypedField::<i64>::new("o", "id");
+        let at = SqlTypedField::<i64>::new("o", "created_at");
+        let inserter = |on_conflict| {
+            let reader = SqlReader::builder(
+                vec![id.erased(), at.erased()],
+                Froms::Table {
+                    table_name: "orders",
+                    alias: "o",
+                },
+            )
+            .wheres(at.v_lt(100))
+            .build();
+            SqlInserter::select("orders_archive", vec!["id", "created_at"], reader)
+                .on_conflict(on_conflict)
+        };
+
+        let seg = inserter(OnConflict::Replace("id".to_owned()))
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            "insert  into orders_archive(id,created_at) select o.id, o.created_at from orders as o  where o.created_at < $1  ON CONFLICT (id) DO UPDATE SET id = excluded.id, created_at = excluded.created_at",
+            seg.seg
+        );
+        assert_eq!(1, seg.values.len());
+
+        #[cfg(feature = "sqlite")]
+        {
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            conn.execute("create table orders (id integer, created_at integer)", [])
+                .unwrap();
+            conn.execute(
+                "create table orders_archive (id integer primary key, created_at integer)",
+                [],
+            )
+            .unwrap();
+            conn.execute("insert into orders values (1, 10), (2, 200), (3, 30)", [])
+                .unwrap();
+            conn.execute("insert into orders_archive values (1, 10)", [])
+                .unwrap();
+            let seg = inserter(OnConflict::Ignore)
+                .into_sql_seg(DbType::Sqlite)
+                .unwrap();
+            let n = conn
+                .execute(&seg.seg, rusqlite::params_from_iter(seg.values))
+                .unwrap();
+            assert_eq!(1, n);
+        }
+
+        let reader = SqlReader::builder(
+            vec![id.erased()],
+            Froms::Table {
+                table_name: "orders",
+                alias: "o",
+            },
+        )
+        .build();
+        assert!(
+            SqlInserter::select("orders_archive", vec!["id", "created_at"], reader)
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+        assert!(
+            inserter(OnConflict::Default)
+                .field("note", "x")
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::marker::PhantomData;
+
+use crate::{
+    ChinSqlError, DbType, Froms, IntoSqlSeg, PlaceHolderType, SqlField, SqlReader,
+    SqlReaderBuilder, SqlSeg, SqlTypedField, SqlValueStatic,
+};
+
+/// A tuple of typed fields which knows how to decode a selected row back into
+/// the tuple of their rust types.
+pub trait SqlProjection<'a> {
+    type Output;
+
+    fn fields(self) -> Vec<SqlField<'a>>;
+
+    fn decode(values: Vec<SqlValueStatic>) -> Result<Self::Output, ChinSqlError>;
+}
+
+macro_rules! sql_projection {
+    ($len:expr, $($tp:ident $var:ident),+) => {
+        impl<'a, $($tp),+> SqlProjection<'a> for ($(SqlTypedField<'a, $tp>,)+)
+        where
+            $($tp: TryFrom<SqlValueStatic, Error = ChinSqlError>,)+
+        {
+            type Output = ($($tp,)+);
+
+            fn fields(self) -> Vec<SqlField<'a>> {
+                let ($($var,)+) = self;
+                vec![$($var.erased()),+]
+            }
+
+            fn decode(values: Vec<SqlValueStatic>) -> Result<Self::Output, ChinSqlError> {
+                if values.len() != $len {
+                    return Err(ChinSqlError::TransformError(format!(
+                        "expect {} columns, but got {}",
+                        $len,
+                        values.len()
+                    )));
+                }
+                let mut values = values.into_iter();
+                Ok(($(<$tp>::try_from(values.next().unwrap())?,)+))
+            }
+        }
+    };
+}
+
+sql_projection!(1, T1 f1);
+sql_projection!(2, T1 f1, T2 f2);
+sql_projection!(3, T1 f1, T2 f2, T3 f3);
+sql_projection!(4, T1 f1, T2 f2, T3 f3, T4 f4);
+sql_projection!(5, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5);
+sql_projection!(6, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5, T6 f6);
+sql_projection!(7, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5, T6 f6, T7 f7);
+sql_projection!(8, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5, T6 f6, T7 f7, T8 f8);
+sql_projection!(9, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5, T6 f6, T7 f7, T8 f8, T9 f9);
+sql_projection!(10, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5, T6 f6, T7 f7, T8 f8, T9 f9, T10 f10);
+sql_projection!(11, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5, T6 f6, T7 f7, T8 f8, T9 f9, T10 f10, T11 f11);
+sql_projection!(12, T1 f1, T2 f2, T3 f3, T4 f4, T5 f5, T6 f6, T7 f7, T8 f8, T9 f9, T10 f10, T11 f11, T12 f12);
+
+/// A `SqlReader` which remembers the types of its selected fields, rows are decoded
+/// into `O`, which is the tuple of the projection or anything built from it.
+pub struct TypedSqlReader<'a, P: SqlProjection<'a>, O = <P as SqlProjection<'a>>::Output> {
+    reader: SqlReader<'a>,
+    output: PhantomData<fn() -> (P, O)>,
+}
+
+impl<'a> SqlReader<'a> {
+    pub fn select<P: SqlProjection<'a>>(
+        projection: P,
+        froms: Froms<'a>,
+    ) -> SqlReaderBuilder<'a, P> {
+        let SqlReaderBuilder { reader, .. } = SqlReader::builder(projection.fields(), froms);
+        SqlReaderBuilder {
+            reader,
+            projection: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: SqlProjection<'a>> SqlReaderBuilder<'a, P> {
+    pub fn build_typed(self) -> TypedSqlReader<'a, P> {
+        TypedSqlReader {
+            reader: self.reader,
+            output: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: SqlProjection<'a>, O: From<P::Output>> TypedSqlReader<'a, P, O> {
+    /// Decode rows into `S` instead, e.g. a struct which implements `From<(T1, T2, ..)>`.
+    pub fn into_struct<S: From<P::Output>>(self) -> TypedSqlReader<'a, P, S> {
+        TypedSqlReader {
+            reader: self.reader,
+            output: PhantomData,
+        }
+    }
+
+    /// Decode the values of one row, in select order.
+    pub fn decode(values: Vec<SqlValueStatic>) -> Result<O, ChinSqlError> {
+        P::decode(values).map(O::from)
+    }
+
+    /// The row decoder, so that it is still at hand after the reader is turned into sql.
+    pub fn decoder(&self) -> fn(Vec<SqlValueStatic>) -> Result<O, ChinSqlError> {
+        Self::decode
+    }
+
+    /// Use it as a derived table, its fields can be referenced by `SqlTypedField::derived`.
+    pub fn as_table(self, alias: &'a str) -> Froms<'a> {
+        Froms::SubQuery {
+            table: Box::new(self.reader),
+            alias,
+        }
+    }
+}
+
+impl<'a, P: SqlProjection<'a>, O> From<TypedSqlReader<'a, P, O>> for SqlReader<'a> {
+    fn from(value: TypedSqlReader<'a, P, O>) -> Self {
+        value.reader
+    }
+}
+
+impl<'a, P: SqlProjection<'a>, O> IntoSqlSeg<'a> for TypedSqlReader<'a, P, O> {
+    fn into_sql_seg2(
+        self,
+        db_type: DbType,
+        pht: &mut PlaceHolderType,
+    ) -> Result<SqlSeg<'a>, ChinSqlError> {
+        self.reader.into_sql_seg2(db_type, pht)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DbType, Froms, IntoSqlSeg, SqlReader, SqlTypedField, SqlValueStatic, str_type::Text,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: i32,
+        name: Text,
+        age: Option<i64>,
+    }
+
+    impl From<(i32, Text, Option<i64>)> for User {
+        fn from((id, name, age): (i32, Text, Option<i64>)) -> Self {
+            Self { id, name, age }
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn select_and_decode() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "create table user (id integer, name text, age integer);
+             insert into user values (1, 'a', null), (2, 'b', 20);",
+        )
+        .unwrap();
+
+        let id = SqlTypedField::<i32>::new("u", "id");
+        let name = SqlTypedField::<Text>::new("u", "name").with_alias("user_name");
+        let age = SqlTypedField::<Option<i64>>::new("u", "age");
+        let inner = SqlReader::select(
+            (id.clone(), name.clone(), age.clone()),
+            Froms::Table {
+                table_name: "user",
+                alias: "u",
+            },
+        )
+        .build_typed()
+        .into_struct::<User>();
+
+        let sub = inner.as_table("s");
+        let reader = SqlReader::select((id.derived("s"), name.derived("s"), age.derived("s")), sub)
+            .order_by([id.derived("s").asc()])
+            .build_typed()
+            .into_struct::<User>();
+        let decode = reader.decoder();
+        let seg = reader.into_sql_seg(DbType::Sqlite).unwrap();
+
+        let mut stmt = conn.prepare(&seg.seg).unwrap();
+        let rows: Vec<User> = stmt
+            .query_map([], |row| {
+                Ok((0..3)
+                    .map(|i| SqlValueStatic::from(row.get::<_, rusqlite::types::Value>(i).unwrap()))
+                    .collect::<Vec<_>>())
+            })
+            .unwrap()
+            .map(|values| decode(values.unwrap()).unwrap())
+            .collect();
+
+        assert_eq!(2, rows.len());
+        assert_eq!(None, rows[0].age);
+        assert_eq!("b", rows[1].name.as_str());
+    }
+}
@@ -0,0 +1,261 @@
+use std::{borrow::Cow, sync::Arc};
+
+use crate::{ChinSqlError, DbType, IntoSqlSeg, PlaceHolderType, SqlSeg, SqlValue, SqlValueStatic};
+
+#[derive(Clone, Debug)]
+pub enum TemplateSlot {
+    /// bound by name on every call
+    Named(Arc<str>),
+    /// a value which was given when the template was compiled
+    Fixed(SqlValueStatic),
+}
+
+/// A query compiled once into owned sql text per [`DbType`], to be bound with fresh values
+/// on every call. Values to bind later are [`SqlValue::Slot`]s in the builder.
+#[derive(Clone, Debug)]
+pub struct SqlTemplate {
+    sqls: Arc<[(DbType, Arc<str>)]>,
+    slots: Arc<[TemplateSlot]>,
+}
+
+impl SqlTemplate {
+    /// Builds the query for every db type, db types which the query does not support are
+    /// skipped.
+    pub fn compile<'a, S, F>(build: F) -> Result<Self, ChinSqlError>
+    where
+        S: IntoSqlSeg<'a>,
+        F: Fn() -> S,
+    {
+        let mut sqls = vec![];
+        let mut slots: Option<Vec<TemplateSlot>> = None;
+        let mut last_err = None;
+        for db_type in [DbType::Sqlite, DbType::Postgres, DbType::DuckDb] {
+            let seg = match build().into_sql_seg(db_type) {
+                Ok(seg) => seg,
+                Err(err) => {
+                    last_err = Some(err);
+                    continue;
+                }
+            };
+            let seg_slots: Vec<TemplateSlot> = seg
+                .values
+                .into_iter()
+                .map(|v| match v {
+                    SqlValue::Slot(name) => TemplateSlot::Named(Arc::from(name.as_ref())),
+                    v => TemplateSlot::Fixed(v.live_static()),
+                })
+                .collect();
+            match &slots {
+                Some(slots) if !same_slots(slots, &seg_slots) => {
+                    return Err(ChinSqlError::BuilderSqlError(format!(
+                        "slots of {db_type:?} differ from other db types"
+                    )));
+                }
+                Some(_) => {}
+                None => slots = Some(seg_slots),
+            }
+            sqls.push((db_type, Arc::from(seg.seg)));
+        }
+
+        match (slots, last_err) {
+            (Some(slots), _) => Ok(Self {
+                sqls: sqls.into(),
+                slots: slots.into(),
+            }),
+            (None, Some(err)) => Err(err),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    pub fn sql(&self, db_type: DbType) -> Option<&str> {
+        self.sqls
+            .iter()
+            .find(|(d, _)| *d == db_type)
+            .map(|(_, sql)| sql.as_ref())
+    }
+
+    pub fn slots(&self) -> &[TemplateSlot] {
+        &self.slots
+    }
+
+    /// Every named slot must be bound, a name used by several slots binds them all.
+    pub fn bind<'v, I, K, V>(&self, params: I) -> Result<BoundSql<'v>, ChinSqlError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<SqlValue<'v>>,
+    {
+        let params: Vec<(K, SqlValue<'v>)> =
+            params.into_iter().map(|(k, v)| (k, v.into())).collect();
+        for (name, _) in &params {
+            let known = self
+                .slots
+                .iter()
+                .any(|s| matches!(s, TemplateSlot::Named(n) if n.as_ref() == name.as_ref()));
+            if !known {
+                return Err(ChinSqlError::BuilderSqlError(format!(
+                    "unknown slot {}",
+                    name.as_ref()
+                )));
+            }
+        }
+
+        let values = self
+            .slots
+            .iter()
+            .map(|slot| match slot {
+                TemplateSlot::Fixed(v) => Ok(v.clone()),
+                TemplateSlot::Named(name) => params
+                    .iter()
+                    .find(|(k, _)| k.as_ref() == name.as_ref())
+                    .map(|(_, v)| v.clone())
+                    .ok_or_else(|| {
+                        ChinSqlError::BuilderSqlError(format!("slot {name} is not bound"))
+                    }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(BoundSql {
+            template: self.clone(),
+            values,
+        })
+    }
+
+    pub fn bind_many<'v, R, I, K, V>(&self, rows: R) -> Result<Vec<BoundSql<'v>>, ChinSqlError>
+    where
+        R: IntoIterator<Item = I>,
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<SqlValue<'v>>,
+    {
+        rows.into_iter().map(|row| self.bind(row)).collect()
+    }
+}
+
+fn same_slots(l: &[TemplateSlot], r: &[TemplateSlot]) -> bool {
+    l.len() == r.len()
+        && l.iter().zip(r).all(|pair| match pair {
+            (TemplateSlot::Named(l), TemplateSlot::Named(r)) => l == r,
+            (TemplateSlot::Fixed(_), TemplateSlot::Fixed(_)) => true,
+            _ => false,
+        })
+}
+
+/// A [`SqlTemplate`] with its values, it can not be nested in other builders.
+#[derive(Clone, Debug)]
+pub struct BoundSql<'v> {
+    template: SqlTemplate,
+    values: Vec<SqlValue<'v>>,
+}
+
+impl<'v> IntoSqlSeg<'v> for BoundSql<'v> {
+    fn into_sql_seg2(
+        self,
+        db_type: DbType,
+        pht: &mut PlaceHolderType,
+    ) -> Result<SqlSeg<'v>, ChinSqlError> {
+        if matches!(pht, PlaceHolderType::DollarNumber(n) if *n != 0) {
+            return Err(ChinSqlError::BuilderSqlError(
+                "template can not be nested".to_owned(),
+            ));
+        }
+        let sql = self.template.sql(db_type).ok_or_else(|| {
+            ChinSqlError::BuilderSqlError(format!("template does not support {db_type:?}"))
+        })?;
+        Ok(SqlSeg::of(sql, self.values))
+    }
+}
+
+impl<'a> SqlValue<'a> {
+    /// A named value bound later through [`SqlTemplate::bind`].
+    pub fn slot<S: Into<Cow<'a, str>>>(name: S) -> Self {
+        SqlValue::Slot(name.into())
+    }
+}
+
+// names the slot before the driver refuses it
+#[cfg(feature = "tokio-postgres")]
+pub(crate) fn check_bound(seg: &SqlSeg) -> Result<(), ChinSqlError> {
+    match seg.values.iter().find(|v| matches!(v, SqlValue::Slot(_))) {
+        Some(SqlValue::Slot(name)) => Err(ChinSqlError::BuilderSqlError(format!(
+            "slot {name} is not bound"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DbType, Froms, IntoSqlSeg, SqlReader, SqlTypedField, str_type::Text};
+
+    use super::SqlTemplate;
+
+    #[test]
+    fn compile_and_bind() {
+        let template = SqlTemplate::compile(|| {
+            let id = SqlTypedField::<i64>::new("n", "id");
+            let body = SqlTypedField::<Text>::new("n", "body");
+            SqlReader::builder(
+                vec![id.erased(), body.erased()],
+                Froms::Table {
+                    table_name: "note",
+                    alias: "n",
+                },
+            )
+            .wheres(id.slot_cmp(">", "min"))
+            .order_by([id.asc()])
+            .limit_slots()
+            .build()
+        })
+        .unwrap();
+        assert_eq!(
+            Some(
+                "select n.id, n.body from note as n  where n.id > $1 order by n.id asc limit $2 offset $3 "
+            ),
+            template.sql(DbType::Postgres)
+        );
+
+        // fixed values of any kind are kept
+        let null = SqlTemplate::compile(|| {
+            crate::SqlBuilder::new()
+                .seg("select ")
+                .val(crate::SqlValue::NullUnknown)
+        })
+        .unwrap();
+        assert_eq!(1, null.slots().len());
+
+        fn assert_send_sync<T: Send + Sync + 'static>(_: &T) {}
+        assert_send_sync(&template);
+
+        assert!(template.bind([("min", 1)]).is_err());
+        assert!(template.bind([("max", 1)]).is_err());
+
+        let bound = template
+            .bind_many([
+                [("min", 0i64), ("limit", 1), ("offset", 0)],
+                [("min", 0), ("limit", 5), ("offset", 1)],
+            ])
+            .unwrap();
+
+        #[cfg(feature = "sqlite")]
+        {
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            conn.execute("create table note (id integer, body text)", [])
+                .unwrap();
+            conn.execute("insert into note values (1, 'a'), (2, 'b'), (3, 'c')", [])
+                .unwrap();
+            let counts: Vec<usize> = bound
+                .into_iter()
+                .map(|b| {
+                    let seg = b.into_sql_seg(DbType::Sqlite).unwrap();
+                    let mut stmt = conn.prepare(&seg.seg).unwrap();
+                    stmt.query(rusqlite::params_from_iter(seg.values))
+                        .unwrap()
+                        .mapped(|_| Ok(()))
+                        .count()
+                })
+                .collect();
+            assert_eq!(vec![1, 2], counts);
+        }
+    }
+}
@@ -1,11 +1,24 @@
-use crate::{ChinSqlError, DbType, IntoSqlSeg};
+use crate::{
+    ChinSqlError, DbType, Froms, IntoSqlSeg, JsonPath, SegOrVal, SqlBuilder, TenantScope,
+    json_path::to_json_text,
+    sql_builder::sqlite_version,
+    tenant::{and_scoped, scope_froms, scope_table},
+};
 
 use super::{SqlSeg, place_hoder::PlaceHolderType, sql_value::SqlValue, wheres::Wheres};
 
+enum Setter<'a> {
+    Column(&'a str, Vec<SegOrVal<'a>>),
+    Json(JsonPath<'a>, SqlValue<'a>),
+}
+
 pub struct SqlUpdater<'a> {
     table: &'a str,
-    setters: Vec<(&'a str, SqlValue<'a>)>,
+    setters: Vec<Setter<'a>>,
+    from: Option<Froms<'a>>,
     wheres: Wheres<'a>,
+    tenant: Option<TenantScope<'a>>,
+    tenant_columns: Option<&'static [&'static str]>,
 }
 
 impl<'a> SqlUpdater<'a> {
@@ -13,13 +26,17 @@ impl<'a> SqlUpdater<'a> {
         SqlUpdater {
             table,
             setters: vec![],
+            from: None,
             wheres: Wheres::and([]),
+            tenant: None,
+            tenant_columns: None,
         }
     }
 
     pub fn set_if_some<T: Into<SqlValue<'a>>>(mut self, key: &'a str, value: Option<T>) -> Self {
         if let Some(v) = value {
-            self.setters.push((key, v.into()));
+            self.setters
+                .push(Setter::Column(key, vec![SegOrVal::val(v)]));
         }
 
         self
@@ -32,26 +49,81 @@ impl<'a> SqlUpdater<'a> {
         trans: F,
     ) -> Self {
         if let Some(v) = value {
-            self.setters.push((key, trans(v).into()));
+            self.setters
+                .push(Setter::Column(key, vec![SegOrVal::val(trans(v))]));
         }
 
         self
     }
 
     pub fn set<T: Into<SqlValue<'a>>>(mut self, key: &'a str, v: T) -> Self {
-        self.setters.push((key, v.into()));
+        self.setters
+            .push(Setter::Column(key, vec![SegOrVal::val(v)]));
+        self
+    }
+
+    /// `key = key + 1`
+    pub fn increment(mut self, key: &'a str) -> Self {
+        self.setters
+            .push(Setter::Column(key, vec![format!("{key} + 1").into()]));
         self
     }
 
+    /// `key = key + ?`
+    pub fn increment_by<T: Into<SqlValue<'a>>>(self, key: &'a str, v: T) -> Self {
+        self.set_expr(key, [format!("{key} + ").into(), SegOrVal::val(v)])
+    }
+
+    /// `key = column`, the column is usually of the table in [`SqlUpdater::from`].
+    pub fn set_column(self, key: &'a str, column: &'a str) -> Self {
+        self.set_expr(key, [column.into()])
+    }
+
+    /// `key = <expr>`, values in the expression are bound.
+    pub fn set_expr<I: IntoIterator<Item = SegOrVal<'a>>>(mut self, key: &'a str, expr: I) -> Self {
+        self.setters
+            .push(Setter::Column(key, expr.into_iter().collect()));
+        self
+    }
+
+    /// `update t set ... from other`, sqlite since 3.33.0.
+    pub fn from<F: Into<Froms<'a>>>(mut self, from: F) -> Self {
+        self.from = Some(from.into());
+        self
+    }
+
+    /// Sets the value at `path` inside the json column, see [`JsonPath`].
+    pub fn json_set<T: Into<SqlValue<'a>>>(mut self, path: JsonPath<'a>, v: T) -> Self {
+        self.setters.push(Setter::Json(path, v.into()));
+        self
+    }
+
+    pub fn table(&self) -> &'a str {
+        self.table
+    }
+
     pub fn r#where(mut self, wheres: Wheres<'a>) -> Self {
         self.wheres = wheres;
         self
     }
+
+    /// Scopes the table and the tables of [`SqlUpdater::from`], see [`TenantScope`].
+    pub fn scope(mut self, scope: &TenantScope<'a>) -> Self {
+        self.tenant = Some(scope.clone());
+        self
+    }
+
+    /// Marks the table as tenant scoped by `columns` without [`crate::register_tenant_table`],
+    /// e.g. the `TENANT_COLUMNS` of a derived table.
+    pub fn tenant_columns(mut self, columns: &'static [&'static str]) -> Self {
+        self.tenant_columns = Some(columns);
+        self
+    }
 }
 
 impl<'a> IntoSqlSeg<'a> for SqlUpdater<'a> {
     fn into_sql_seg2(
-        self,
+        mut self,
         db_type: DbType,
         pht: &mut PlaceHolderType,
     ) -> Result<SqlSeg<'a>, ChinSqlError> {
@@ -60,6 +132,22 @@ impl<'a> IntoSqlSeg<'a> for SqlUpdater<'a> {
                 "update setters is empty".to_owned(),
             ));
         }
+        if self.wheres.empty() {
+            return Err(ChinSqlError::FilterBuildError(
+                "filter_is_empty".to_string(),
+            ));
+        }
+        let scope = self.tenant.as_ref();
+        let mut scoped = vec![scope_table(
+            scope,
+            self.table,
+            self.tenant_columns,
+            Some(self.table),
+        )?];
+        if let Some(from) = self.from.as_mut() {
+            scope_froms(from, scope, &mut scoped)?;
+        }
+        let wheres = and_scoped(self.wheres, scoped);
 
         let mut sb = String::new();
         let mut values: Vec<SqlValue<'a>> = Vec::new();
@@ -68,17 +156,48 @@ impl<'a> IntoSqlSeg<'a> for SqlUpdater<'a> {
         sb.push_str(self.table);
         sb.push_str(" set ");
 
-        let fields: Vec<String> = self
-            .setters
-            .into_iter()
-            .map(|(key, v)| {
-                values.push(v);
-                format!(" {} = {} ", key, pht.next_ph())
-            })
-            .collect();
+        let mut fields: Vec<String> = Vec::with_capacity(self.setters.len());
+        for setter in self.setters {
+            match setter {
+                Setter::Column(key, expr) => {
+                    let mut field = format!(" {key} = ");
+                    for sov in expr {
+                        match sov {
+                            SegOrVal::Str(seg) => field.push_str(&seg),
+                            SegOrVal::Val(v) => {
+                                values.push(v);
+                                field.push_str(&pht.next_ph());
+                            }
+                        }
+                    }
+                    field.push(' ');
+                    fields.push(field);
+                }
+                Setter::Json(path, v) => {
+                    values.push(SqlValue::Str(to_json_text(&v)?.into()));
+                    fields.push(format!(
+                        " {} = {} ",
+                        path.column(),
+                        path.set_sql(db_type, &pht.next_ph())?
+                    ));
+                }
+            }
+        }
         sb.push_str(fields.join(", ").as_str());
 
-        if let Some(filters) = self.wheres.build(db_type, pht) {
+        if let Some(from) = self.from {
+            if db_type == DbType::Sqlite && sqlite_version() < 3_033_000 {
+                return Err(ChinSqlError::BuilderSqlError(
+                    "update from needs sqlite 3.33.0".to_owned(),
+                ));
+            }
+            let from = SqlBuilder::from(from).into_sql_seg2(db_type, pht)?;
+            sb.push_str(" from ");
+            sb.push_str(&from.seg);
+            values.extend(from.values);
+        }
+
+        if let Some(filters) = wheres.build(db_type, pht)? {
             sb.push_str(" where ");
             sb.push_str(filters.seg.as_str());
 
@@ -92,3 +211,63 @@ impl<'a> IntoSqlSeg<'a> for SqlUpdater<'a> {
         Ok(SqlSeg::of(sb, values))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{DbType, Froms, IntoSqlSeg, SegOrVal, SqlUpdater, Wheres};
+
+    #[test]
+    fn setter_expressions() {
+        let updater = || {
+            SqlUpdater::new("stock")
+                .increment_by("count", 3)
+                .set_expr("price", ["price * ".into(), SegOrVal::val(2)])
+                .set_column("name", "s.name")
+                .from(Froms::Table {
+                    table_name: "source",
+                    alias: "s",
+                })
+                .r#where(Wheres::and([
+                    Wheres::Raw("stock.id = s.id".into()),
+                    Wheres::equal("s.batch", 7),
+                ]))
+        };
+        let seg = updater().into_sql_seg(DbType::Postgres).unwrap();
+        assert_eq!(
+            " update stock set  count = count + $1 ,  price = price * $2 ,  name = s.name  from source as s  where  stock.id = s.id  and s.batch = $3",
+            seg.seg
+        );
+
+        #[cfg(feature = "sqlite")]
+        {
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            conn.execute_batch(
+                "create table stock (id integer, count integer, price integer, name text);
+                create table source (id integer, batch integer, name text);
+                insert into stock values (1, 1, 10, 'a'), (2, 1, 10, 'b');
+                insert into source values (1, 7, 'x'), (2, 8, 'y');",
+            )
+            .unwrap();
+            let seg = updater().into_sql_seg(DbType::Sqlite).unwrap();
+            let n = conn
+                .execute(&seg.seg, rusqlite::params_from_iter(seg.values))
+                .unwrap();
+            assert_eq!(1, n);
+            let row: (i64, i64, String) = conn
+                .query_row(
+                    "select count, price, name from stock where id = 1",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+                )
+                .unwrap();
+            assert_eq!((4, 20, "x".to_owned()), row);
+        }
+
+        assert!(
+            SqlUpdater::new("stock")
+                .increment_by("count", 1)
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use duckdb::{
+    ToSql,
+    types::{TimeUnit, ToSqlOutput, Value, ValueRef},
+};
+
+use crate::ChinSqlError;
+
+use super::{SqlValue, SqlValueStatic};
+
+fn micros(unit: TimeUnit, v: i64) -> i64 {
+    match unit {
+        TimeUnit::Second => v * 1_000_000,
+        TimeUnit::Millisecond => v * 1_000,
+        TimeUnit::Microsecond => v,
+        TimeUnit::Nanosecond => v / 1_000,
+    }
+}
+
+impl<'a> ToSql for SqlValue<'a> {
+    fn to_sql(&self) -> duckdb::Result<ToSqlOutput<'_>> {
+        let value = match self {
+            SqlValue::Bool(v) => Value::Boolean(*v),
+            SqlValue::I8(v) => Value::TinyInt(*v),
+            SqlValue::I16(v) => Value::SmallInt(*v),
+            SqlValue::I32(v) => Value::Int(*v),
+            SqlValue::I64(v) => Value::BigInt(*v),
+            SqlValue::F64(v) => Value::Double(*v),
+            SqlValue::Str(v) => return Ok(ToSqlOutput::Borrowed(ValueRef::Text(v.as_bytes()))),
+            // timestamps are bound as utc, the column type decides on the time zone
+            SqlValue::FixedOffset(v) => {
+                Value::Timestamp(TimeUnit::Microsecond, v.timestamp_micros())
+            }
+            SqlValue::Utc(v) => Value::Timestamp(TimeUnit::Microsecond, v.timestamp_micros()),
+            SqlValue::Blob(v) => return Ok(ToSqlOutput::Borrowed(ValueRef::Blob(v))),
+            SqlValue::Null(_) | SqlValue::NullUnknown => Value::Null,
+            SqlValue::Slot(name) => {
+                return Err(duckdb::Error::ToSqlConversionFailure(
+                    format!("slot {name} is not bound").into(),
+                ));
+            }
+        };
+        Ok(ToSqlOutput::Owned(value))
+    }
+}
+
+impl TryFrom<Value> for SqlValueStatic {
+    type Error = ChinSqlError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let out_of_range = |v: &dyn std::fmt::Debug| {
+            ChinSqlError::TransformError(format!("{v:?} is out of the range of i64"))
+        };
+        Ok(match value {
+            Value::Null => SqlValue::NullUnknown,
+            Value::Boolean(v) => SqlValue::Bool(v),
+            Value::TinyInt(v) => SqlValue::I8(v),
+            Value::SmallInt(v) => SqlValue::I16(v),
+            Value::Int(v) => SqlValue::I32(v),
+            Value::BigInt(v) => SqlValue::I64(v),
+            Value::UTinyInt(v) => SqlValue::I16(v.into()),
+            Value::USmallInt(v) => SqlValue::I32(v.into()),
+            Value::UInt(v) => SqlValue::I64(v.into()),
+            // sums of integers are huge ints
+            Value::UBigInt(v) => SqlValue::I64(i64::try_from(v).map_err(|_| out_of_range(&v))?),
+            Value::HugeInt(v) => SqlValue::I64(i64::try_from(v).map_err(|_| out_of_range(&v))?),
+            Value::Float(v) => SqlValue::F64(v.into()),
+            Value::Double(v) => SqlValue::F64(v),
+            Value::Decimal(v) => SqlValue::F64(
+                v.to_string()
+                    .parse()
+                    .map_err(|_| ChinSqlError::TransformError(format!("{v} is not a valid f64")))?,
+            ),
+            Value::Text(v) | Value::Enum(v) => SqlValue::Str(v.into()),
+            Value::Blob(v) => SqlValue::Blob(v.into()),
+            Value::Timestamp(unit, v) => SqlValue::Utc(
+                DateTime::<Utc>::from_timestamp_micros(micros(unit, v)).ok_or_else(|| {
+                    ChinSqlError::TransformError(format!("{v} is not a valid timestamp"))
+                })?,
+            ),
+            Value::Date32(days) => {
+                SqlValue::Utc(DateTime::UNIX_EPOCH + TimeDelta::days(days.into()))
+            }
+            other => {
+                return Err(ChinSqlError::TransformError(format!(
+                    "{other:?} can not be converted to a sql value"
+                )));
+            }
+        })
+    }
+}
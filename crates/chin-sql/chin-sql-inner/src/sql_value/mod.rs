@@ -4,17 +4,24 @@ mod sqlite;
 #[cfg(feature = "postgres")]
 mod postgres;
 
-use std::{borrow::Cow, collections::HashMap, sync::Arc};
+#[cfg(feature = "duckdb")]
+mod duckdb;
+
+mod row;
+
+use std::borrow::Cow;
 
 pub mod str_type;
 pub mod time_type;
 
+pub use row::*;
+
 use chrono::{DateTime, FixedOffset, Utc};
 use sqlite::sqltype::Timestamptz;
 
 use crate::{
     ChinSqlError, LogicFieldType,
-    str_type::{Text, Varchar},
+    str_type::{Text, Varchar, VarcharPolicy},
     time_type::TID,
 };
 
@@ -32,15 +39,12 @@ pub enum SqlValue<'a> {
     Blob(Cow<'a, [u8]>),
     Null(LogicFieldType),
     NullUnknown,
+    /// Placeholder of a [`crate::SqlTemplate`], it must be bound before execution.
+    Slot(Cow<'a, str>),
 }
 
 pub type SqlValueStatic = SqlValue<'static>;
 
-#[derive(Clone, Debug)]
-pub struct SqlValueRow {
-    pub row: HashMap<Arc<str>, SqlValue<'static>>,
-}
-
 impl<'a> SqlValue<'a> {
     pub fn live_static(self) -> SqlValue<'static> {
         match self {
@@ -55,7 +59,8 @@ impl<'a> SqlValue<'a> {
             SqlValue::Blob(cow) => SqlValue::Blob(Cow::Owned(cow.to_vec())),
             SqlValue::Str(cow) => SqlValue::Str(Cow::Owned(cow.into_owned())),
             SqlValue::Null(logic_field_type) => SqlValue::Null(logic_field_type),
-            SqlValue::NullUnknown => unreachable!(),
+            SqlValue::Slot(cow) => SqlValue::Slot(Cow::Owned(cow.into_owned())),
+            SqlValue::NullUnknown => SqlValue::NullUnknown,
         }
     }
 }
@@ -120,24 +125,60 @@ impl<'a> From<DateTime<FixedOffset>> for SqlValue<'a> {
     }
 }
 
+impl<'a> From<DateTime<Utc>> for SqlValue<'a> {
+    fn from(val: DateTime<Utc>) -> Self {
+        SqlValue::Utc(val)
+    }
+}
+
 impl<'a> From<f64> for SqlValue<'a> {
     fn from(val: f64) -> Self {
         SqlValue::F64(val)
     }
 }
 
-impl<const LIMIT: usize> From<Varchar<LIMIT>> for SqlValue<'_> {
-    fn from(value: Varchar<LIMIT>) -> Self {
+impl<const LIMIT: usize, P> From<Varchar<LIMIT, P>> for SqlValue<'_> {
+    fn from(value: Varchar<LIMIT, P>) -> Self {
         Self::Str(Cow::Owned(value.0.to_string()))
     }
 }
 
+impl<const LIMIT: usize, P: VarcharPolicy> From<Option<Varchar<LIMIT, P>>> for SqlValue<'_> {
+    fn from(value: Option<Varchar<LIMIT, P>>) -> Self {
+        match value {
+            Some(v) => v.into(),
+            None => Self::Null(LogicFieldType::Varchar(
+                Varchar::<LIMIT, P>::LENGTH,
+                P::UNIT,
+            )),
+        }
+    }
+}
+
+impl<'a, const LIMIT: usize, P: VarcharPolicy> TryFrom<SqlValue<'a>> for Option<Varchar<LIMIT, P>> {
+    type Error = ChinSqlError;
+
+    fn try_from(value: SqlValue<'a>) -> Result<Self, Self::Error> {
+        match value {
+            SqlValue::Null(_) | SqlValue::NullUnknown => Ok(None),
+            other => other.try_into().map(Some),
+        }
+    }
+}
+
 impl From<Text> for SqlValue<'_> {
     fn from(value: Text) -> Self {
         Self::Str(Cow::Owned(value.0.to_string()))
     }
 }
 
+// sqlite hands every integer back as i64
+fn narrow_int<T: TryFrom<i64, Error = std::num::TryFromIntError>>(
+    v: i64,
+) -> Result<T, ChinSqlError> {
+    T::try_from(v).map_err(|err| ChinSqlError::TransformError(format!("{v}: {err}")))
+}
+
 macro_rules! try_from_sql_value {
     ($tp:ty, $rlt:expr, $($variant:ident => $conv:expr),*) => {
         impl<'a> TryFrom<SqlValue<'a>> for $tp {
@@ -206,12 +247,27 @@ try_from_sql_value!(DateTime<FixedOffset>, LogicFieldType::Timestamptz,
     FixedOffset => |v: DateTime<FixedOffset>| Ok(v),
     I64 => |v: i64| Timestamptz::try_from(v).map(|tz| *tz)
 );
+try_from_sql_value!(DateTime<Utc>, LogicFieldType::Timestamp,
+    Utc => |v: DateTime<Utc>| Ok(v),
+    I64 => |v: i64| Timestamptz::try_from(v).map(|tz| tz.to_utc())
+);
 try_from_sql_value!(bool, LogicFieldType::Bool,
     Bool => |v: bool| Ok(v),
     I64 => |v: i64| Ok(v != 0)
 );
 try_from_sql_value!(i64, LogicFieldType::I64, I64 => |v: i64| Ok(v));
-try_from_sql_value!(i32, LogicFieldType::I32, I32 => |v: i32| Ok(v));
+try_from_sql_value!(i32, LogicFieldType::I32,
+    I32 => |v: i32| Ok(v),
+    I64 => |v: i64| narrow_int(v)
+);
+try_from_sql_value!(i16, LogicFieldType::I16,
+    I16 => |v: i16| Ok(v),
+    I64 => |v: i64| narrow_int(v)
+);
+try_from_sql_value!(i8, LogicFieldType::I8,
+    I8 => |v: i8| Ok(v),
+    I64 => |v: i64| narrow_int(v)
+);
 try_from_sql_value!(f64, LogicFieldType::F64, F64 => |v: f64| Ok(v));
 try_from_sql_value!(Cow<'a, str>, LogicFieldType::Text, Str => |v: Cow<'a, str>| Ok(v));
 try_from_sql_value!(String, LogicFieldType::Text,
@@ -1,8 +1,51 @@
+use bytes::BytesMut;
 use chrono::{DateTime, FixedOffset, Utc};
-use postgres_types::ToSql;
+use postgres_types::{IsNull, ToSql, Type, to_sql_checked};
 
 use crate::{LogicFieldType, SqlValue};
 
+/// A null of whatever type the parameter has, for [`SqlValue::NullUnknown`], e.g. read from
+/// an untyped column or from sqlite.
+#[derive(Debug)]
+struct AnyNull;
+
+impl ToSql for AnyNull {
+    fn to_sql(
+        &self,
+        _: &Type,
+        _: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(IsNull::Yes)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+/// Stands in for an unbound [`SqlValue::Slot`] and fails to serialize, like the sqlite
+/// `ToSql` impl does.
+#[derive(Debug)]
+struct Unbound;
+
+impl ToSql for Unbound {
+    fn to_sql(
+        &self,
+        _: &Type,
+        _: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        Err("slot is not bound".into())
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
 impl<'a> From<&'a SqlValue<'a>> for &'a (dyn ToSql + Sync + Send) {
     fn from(val: &'a SqlValue<'a>) -> Self {
         match val {
@@ -27,17 +70,71 @@ impl<'a> From<&'a SqlValue<'a>> for &'a (dyn ToSql + Sync + Send) {
                 LogicFieldType::Blob => &None::<Vec<u8>>,
                 LogicFieldType::Timestamptz => &None::<DateTime<FixedOffset>>,
                 LogicFieldType::Timestamp => &None::<DateTime<Utc>>,
-                LogicFieldType::Varchar(_) => &None::<String>,
+                LogicFieldType::Varchar(..) => &None::<String>,
             },
-            SqlValue::NullUnknown => unreachable!(),
+            SqlValue::NullUnknown => &AnyNull,
+            SqlValue::Slot(_) => &Unbound,
         }
     }
 }
 
 pub mod from_sql {
-    use postgres_types::FromSql;
+    use std::borrow::Cow;
 
-    use crate::str_type::{Text, Varchar};
+    use chrono::{DateTime, FixedOffset, NaiveDateTime};
+    use postgres_types::{FromSql, Type};
+
+    use crate::{
+        LogicFieldType, SqlValue, SqlValueStatic,
+        str_type::{Text, Varchar, VarcharPolicy},
+    };
+
+    fn logic_type(ty: &Type) -> Option<LogicFieldType> {
+        Some(match *ty {
+            Type::BOOL => LogicFieldType::Bool,
+            Type::CHAR => LogicFieldType::I8,
+            Type::INT2 => LogicFieldType::I16,
+            Type::INT4 => LogicFieldType::I32,
+            Type::INT8 => LogicFieldType::I64,
+            Type::FLOAT4 | Type::FLOAT8 => LogicFieldType::F64,
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => LogicFieldType::Text,
+            Type::BYTEA => LogicFieldType::Blob,
+            Type::TIMESTAMPTZ => LogicFieldType::Timestamptz,
+            Type::TIMESTAMP => LogicFieldType::Timestamp,
+            _ => return None,
+        })
+    }
+
+    impl<'a> FromSql<'a> for SqlValueStatic {
+        fn from_sql(
+            ty: &Type,
+            raw: &'a [u8],
+        ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+            Ok(match *ty {
+                Type::BOOL => SqlValue::Bool(bool::from_sql(ty, raw)?),
+                Type::CHAR => SqlValue::I8(i8::from_sql(ty, raw)?),
+                Type::INT2 => SqlValue::I16(i16::from_sql(ty, raw)?),
+                Type::INT4 => SqlValue::I32(i32::from_sql(ty, raw)?),
+                Type::INT8 => SqlValue::I64(i64::from_sql(ty, raw)?),
+                Type::FLOAT4 => SqlValue::F64(f32::from_sql(ty, raw)? as f64),
+                Type::FLOAT8 => SqlValue::F64(f64::from_sql(ty, raw)?),
+                Type::BYTEA => SqlValue::Blob(Cow::Owned(Vec::<u8>::from_sql(ty, raw)?)),
+                Type::TIMESTAMPTZ => {
+                    SqlValue::FixedOffset(DateTime::<FixedOffset>::from_sql(ty, raw)?)
+                }
+                Type::TIMESTAMP => SqlValue::Utc(NaiveDateTime::from_sql(ty, raw)?.and_utc()),
+                _ => SqlValue::Str(Cow::Owned(String::from_sql(ty, raw)?)),
+            })
+        }
+
+        fn from_sql_null(ty: &Type) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+            Ok(logic_type(ty).map_or(SqlValue::NullUnknown, SqlValue::Null))
+        }
+
+        fn accepts(ty: &Type) -> bool {
+            logic_type(ty).is_some()
+        }
+    }
 
     impl<'a> FromSql<'a> for Text {
         fn from_sql(
@@ -53,7 +150,7 @@ pub mod from_sql {
         }
     }
 
-    impl<'a, const LIMIT: usize> FromSql<'a> for Varchar<LIMIT> {
+    impl<'a, const LIMIT: usize, P: VarcharPolicy> FromSql<'a> for Varchar<LIMIT, P> {
         fn from_sql(
             ty: &postgres_types::Type,
             raw: &'a [u8],
@@ -70,3 +167,32 @@ pub mod from_sql {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use postgres_types::{IsNull, ToSql, Type};
+
+    use crate::SqlValue;
+
+    #[test]
+    fn bind_null_unknown() {
+        let value = SqlValue::NullUnknown;
+        let param = <&(dyn ToSql + Sync + Send)>::from(&value);
+        for ty in [Type::INT4, Type::TEXT, Type::TIMESTAMPTZ, Type::BYTEA] {
+            let mut out = BytesMut::new();
+            assert!(matches!(
+                param.to_sql_checked(&ty, &mut out).unwrap(),
+                IsNull::Yes
+            ));
+        }
+    }
+
+    #[test]
+    fn reject_slot() {
+        let value = SqlValue::Slot("id".into());
+        let param = <&(dyn ToSql + Sync + Send)>::from(&value);
+        let mut out = BytesMut::new();
+        assert!(param.to_sql_checked(&Type::INT4, &mut out).is_err());
+    }
+}
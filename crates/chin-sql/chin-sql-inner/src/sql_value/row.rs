@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+use crate::ChinSqlError;
+
+use super::{SqlValue, SqlValueStatic};
+
+/// A row of a query result, the values keep the order of the selected columns.
+#[derive(Clone, Debug)]
+pub struct SqlValueRow {
+    columns: Arc<[Arc<str>]>,
+    values: Vec<SqlValueStatic>,
+}
+
+impl SqlValueRow {
+    /// `columns` is shared by all rows of a result.
+    pub fn new(columns: Arc<[Arc<str>]>, values: Vec<SqlValueStatic>) -> Self {
+        Self { columns, values }
+    }
+
+    pub fn columns(&self) -> &[Arc<str>] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[SqlValueStatic] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SqlValueStatic)> {
+        self.columns.iter().map(|c| c.as_ref()).zip(&self.values)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&SqlValueStatic> {
+        self.columns
+            .iter()
+            .position(|c| c.as_ref() == name)
+            .map(|i| &self.values[i])
+    }
+
+    pub fn try_get<T>(&self, name: &str) -> Result<T, ChinSqlError>
+    where
+        T: TryFrom<SqlValueStatic, Error = ChinSqlError>,
+    {
+        let value = self
+            .value(name)
+            .ok_or_else(|| ChinSqlError::TransformError(format!("no column {name}")))?;
+        T::try_from(value.clone())
+    }
+
+    /// Panics if the column is missing or can not be converted, see [`SqlValueRow::try_get`].
+    pub fn get<T>(&self, name: &str) -> T
+    where
+        T: TryFrom<SqlValueStatic, Error = ChinSqlError>,
+    {
+        match self.try_get(name) {
+            Ok(v) => v,
+            Err(err) => panic!("unable to get {name}: {err}"),
+        }
+    }
+}
+
+/// Timestamps are RFC 3339 strings, blobs are base64 strings.
+impl<'a> Serialize for SqlValue<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SqlValue::Bool(v) => serializer.serialize_bool(*v),
+            SqlValue::I8(v) => serializer.serialize_i8(*v),
+            SqlValue::I16(v) => serializer.serialize_i16(*v),
+            SqlValue::I32(v) => serializer.serialize_i32(*v),
+            SqlValue::I64(v) => serializer.serialize_i64(*v),
+            SqlValue::F64(v) => serializer.serialize_f64(*v),
+            SqlValue::Str(v) => serializer.serialize_str(v),
+            SqlValue::FixedOffset(v) => serializer.serialize_str(&v.to_rfc3339()),
+            SqlValue::Utc(v) => serializer.serialize_str(&v.to_rfc3339()),
+            SqlValue::Blob(v) => serializer.serialize_str(&STANDARD.encode(v)),
+            SqlValue::Null(_) | SqlValue::NullUnknown => serializer.serialize_none(),
+            SqlValue::Slot(name) => Err(serde::ser::Error::custom(format!(
+                "slot {name} is not bound"
+            ))),
+        }
+    }
+}
+
+impl Serialize for SqlValueRow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (column, value) in self.iter() {
+            map.serialize_entry(column, value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, sync::Arc};
+
+    use chrono::DateTime;
+
+    use crate::{LogicFieldType, SqlValue};
+
+    use super::SqlValueRow;
+
+    #[test]
+    fn row_accessors() {
+        let row = SqlValueRow::new(
+            Arc::from(["z", "a", "at", "data", "gone"].map(Arc::from)),
+            vec![
+                SqlValue::I64(7),
+                SqlValue::Str(Cow::Borrowed("x")),
+                SqlValue::FixedOffset(
+                    DateTime::parse_from_rfc3339("2024-05-01T08:00:00+08:00").unwrap(),
+                ),
+                SqlValue::Blob(Cow::Borrowed(b"hi")),
+                SqlValue::Null(LogicFieldType::Text),
+            ],
+        );
+        assert_eq!(7, row.get::<i64>("z"));
+        assert_eq!(Some(7), row.get::<Option<i32>>("z"));
+        assert_eq!("x", row.get::<String>("a"));
+        assert_eq!(None, row.get::<Option<String>>("gone"));
+        assert!(row.try_get::<String>("z").is_err());
+        assert!(row.try_get::<i64>("missing").is_err());
+        assert_eq!(
+            r#"{"z":7,"a":"x","at":"2024-05-01T08:00:00+08:00","data":"aGk=","gone":null}"#,
+            serde_json::to_string(&row).unwrap()
+        );
+    }
+}
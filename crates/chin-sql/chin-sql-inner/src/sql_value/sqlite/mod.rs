@@ -5,6 +5,8 @@ use rusqlite::{
     types::{ToSqlOutput, Value},
 };
 
+use crate::ChinSqlError;
+
 use super::{SqlValue, SqlValueStatic};
 
 pub mod sqltype;
@@ -28,13 +30,18 @@ impl<'a> ToSql for SqlValue<'a> {
             SqlValue::Blob(cow) => cow.to_sql(),
             SqlValue::Null(_) => Ok(ToSqlOutput::Owned(Value::Null)),
             SqlValue::NullUnknown => Ok(ToSqlOutput::Owned(Value::Null)),
+            SqlValue::Slot(name) => Err(rusqlite::Error::ToSqlConversionFailure(
+                format!("slot {name} is not bound").into(),
+            )),
         }
     }
 }
 
-impl<'a> From<SqlValue<'a>> for Value {
-    fn from(value: SqlValue<'a>) -> Self {
-        match value {
+impl<'a> TryFrom<SqlValue<'a>> for Value {
+    type Error = ChinSqlError;
+
+    fn try_from(value: SqlValue<'a>) -> Result<Self, Self::Error> {
+        Ok(match value {
             SqlValue::Bool(v) => Value::from(v),
             SqlValue::I8(v) => Value::from(v),
             SqlValue::I16(v) => Value::from(v),
@@ -49,7 +56,12 @@ impl<'a> From<SqlValue<'a>> for Value {
             SqlValue::Blob(v) => Value::from(v.to_vec()),
             SqlValue::Null(_) => Value::Null,
             SqlValue::NullUnknown => Value::Null,
-        }
+            SqlValue::Slot(name) => {
+                return Err(ChinSqlError::BuilderSqlError(format!(
+                    "slot {name} is not bound"
+                )));
+            }
+        })
     }
 }
 
@@ -69,7 +81,15 @@ impl From<Value> for SqlValueStatic {
 mod tests {
     use chrono::{DateTime, Local};
 
-    use crate::sql_value::sqlite::sqltype::Timestamptz;
+    use rusqlite::types::Value;
+
+    use crate::{SqlValue, sql_value::sqlite::sqltype::Timestamptz};
+
+    #[test]
+    fn reject_slot() {
+        assert!(Value::try_from(SqlValue::slot("id")).is_err());
+        assert_eq!(Value::Null, Value::try_from(SqlValue::NullUnknown).unwrap());
+    }
 
     #[test]
     fn test_convert() {
@@ -1,17 +1,115 @@
-use std::fmt::Display;
+use std::{fmt::Display, marker::PhantomData};
 
 use chin_tools_types::SharedStr;
 use serde::{Deserialize, Deserializer, Serialize, de};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{ChinSqlError, SqlValue};
 
+/// How the length of a `Varchar` is measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthUnit {
+    Bytes,
+    Chars,
+    Graphemes,
+}
+
+/// What to do with a value which is longer than the limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    Reject,
+    Truncate,
+}
+
+impl LengthUnit {
+    pub fn len(self, s: &str) -> usize {
+        match self {
+            LengthUnit::Bytes => s.len(),
+            LengthUnit::Chars => s.chars().count(),
+            LengthUnit::Graphemes => s.graphemes(true).count(),
+        }
+    }
+
+    /// Longest prefix of `s` which fits into `limit` units, never splitting a char
+    /// (or a grapheme cluster for `Graphemes`).
+    pub fn truncate(self, s: &str, limit: usize) -> &str {
+        let end = match self {
+            LengthUnit::Bytes => {
+                if s.len() <= limit {
+                    s.len()
+                } else {
+                    (0..=limit)
+                        .rev()
+                        .find(|i| s.is_char_boundary(*i))
+                        .unwrap_or(0)
+                }
+            }
+            LengthUnit::Chars => s.char_indices().nth(limit).map_or(s.len(), |(i, _)| i),
+            LengthUnit::Graphemes => s
+                .grapheme_indices(true)
+                .nth(limit)
+                .map_or(s.len(), |(i, _)| i),
+        };
+        &s[..end]
+    }
+}
+
+pub trait VarcharPolicy {
+    const UNIT: LengthUnit;
+    const OVERFLOW: Overflow;
+}
+
+/// Count UTF-8 bytes, reject longer values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bytes;
+
+/// Count unicode scalar values, reject longer values. Same as Postgres `varchar(n)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Chars;
+
+/// Count extended grapheme clusters, reject longer values.
+///
+/// Only checked on the rust side, the column is a plain `TEXT` without a length check on
+/// Postgres and sqlite, so rows written by other clients are unbounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Graphemes;
+
+/// Same unit as `P`, but cut longer values instead of rejecting them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Truncate<P>(PhantomData<P>);
+
+impl VarcharPolicy for Bytes {
+    const UNIT: LengthUnit = LengthUnit::Bytes;
+    const OVERFLOW: Overflow = Overflow::Reject;
+}
+
+impl VarcharPolicy for Chars {
+    const UNIT: LengthUnit = LengthUnit::Chars;
+    const OVERFLOW: Overflow = Overflow::Reject;
+}
+
+impl VarcharPolicy for Graphemes {
+    const UNIT: LengthUnit = LengthUnit::Graphemes;
+    const OVERFLOW: Overflow = Overflow::Reject;
+}
+
+impl<P: VarcharPolicy> VarcharPolicy for Truncate<P> {
+    const UNIT: LengthUnit = P::UNIT;
+    const OVERFLOW: Overflow = Overflow::Truncate;
+}
+
+/// A string of at most `LIMIT` units of the policy `P`.
+///
+/// The default policy is [`Chars`], like Postgres `varchar(n)`. Before the policies the limit
+/// counted UTF-8 bytes, [`Bytes`] keeps that. A `LIMIT` above `u16::MAX` does not compile
+/// once the column type is needed.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Varchar<const LIMIT: usize>(pub(crate) SharedStr);
+pub struct Varchar<const LIMIT: usize, P = Chars>(pub(crate) SharedStr, PhantomData<P>);
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Text(pub(crate) SharedStr);
 
-impl<const LIMIT: usize> Serialize for Varchar<LIMIT> {
+impl<const LIMIT: usize, P> Serialize for Varchar<LIMIT, P> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -20,57 +118,34 @@ impl<const LIMIT: usize> Serialize for Varchar<LIMIT> {
     }
 }
 
-impl<'de, const LIMIT: usize> Deserialize<'de> for Varchar<LIMIT> {
-    fn deserialize<D>(deserializer: D) -> Result<Varchar<LIMIT>, D::Error>
+impl<'de, const LIMIT: usize, P: VarcharPolicy> Deserialize<'de> for Varchar<LIMIT, P> {
+    fn deserialize<D>(deserializer: D) -> Result<Varchar<LIMIT, P>, D::Error>
     where
         D: Deserializer<'de>,
     {
         let o: String = String::deserialize(deserializer)?;
-        if o.len() > LIMIT {
-            return Err(de::Error::custom(format!(
-                "out of ranch: {} > {}",
-                o.len(),
-                LIMIT
-            )));
-        }
-        Ok(Self(o.into()))
+        Self::checked(o).map_err(de::Error::custom)
     }
 }
 
-impl<const LIMIT: usize> TryFrom<String> for Varchar<LIMIT> {
+impl<const LIMIT: usize, P: VarcharPolicy> TryFrom<String> for Varchar<LIMIT, P> {
     type Error = ChinSqlError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        if value.len() > LIMIT {
-            return Err(ChinSqlError::TransformError(format!(
-                "out of ranch: {} > {}",
-                value.len(),
-                LIMIT
-            )));
-        }
-
-        Ok(Self(SharedStr::from(value)))
+        Self::checked(value)
     }
 }
 
-impl<const LIMIT: usize> TryFrom<&'static str> for Varchar<LIMIT> {
+impl<const LIMIT: usize, P: VarcharPolicy> TryFrom<&'static str> for Varchar<LIMIT, P> {
     type Error = ChinSqlError;
 
     fn try_from(value: &'static str) -> Result<Self, Self::Error> {
-        if value.len() > LIMIT {
-            return Err(ChinSqlError::TransformError(format!(
-                "out of ranch: {} > {}",
-                value.len(),
-                LIMIT
-            )));
-        }
-
-        Ok(Self(SharedStr::from(value)))
+        Self::checked(value)
     }
 }
 
-impl<const LIMIT: usize> From<Varchar<LIMIT>> for String {
-    fn from(value: Varchar<LIMIT>) -> Self {
+impl<const LIMIT: usize, P> From<Varchar<LIMIT, P>> for String {
+    fn from(value: Varchar<LIMIT, P>) -> Self {
         value.0.to_string()
     }
 }
@@ -106,13 +181,37 @@ impl From<Text> for String {
     }
 }
 
-impl<const LIMIT: usize> Varchar<LIMIT> {
+impl<const LIMIT: usize, P> Varchar<LIMIT, P> {
+    /// `LIMIT` as the length of [`crate::LogicFieldType::Varchar`].
+    pub(crate) const LENGTH: u16 = {
+        assert!(LIMIT <= u16::MAX as usize, "varchar limit above u16::MAX");
+        LIMIT as u16
+    };
+
     pub fn as_str(&self) -> &str {
         &self.0
     }
+}
 
+impl<const LIMIT: usize, P: VarcharPolicy> Varchar<LIMIT, P> {
+    /// Cut `s` down to `LIMIT` in the unit of the policy, whatever its overflow rule is.
     pub fn limit<S: AsRef<str>>(s: S) -> Self {
-        Self(s.as_ref()[0..LIMIT].into())
+        Self(P::UNIT.truncate(s.as_ref(), LIMIT).into(), PhantomData)
+    }
+
+    fn checked<S: AsRef<str>>(value: S) -> Result<Self, ChinSqlError> {
+        let value = value.as_ref();
+        let len = P::UNIT.len(value);
+        if len <= LIMIT {
+            return Ok(Self(value.into(), PhantomData));
+        }
+        match P::OVERFLOW {
+            Overflow::Reject => Err(ChinSqlError::TransformError(format!(
+                "out of range: {len} > {LIMIT} ({:?})",
+                P::UNIT
+            ))),
+            Overflow::Truncate => Ok(Self::limit(value)),
+        }
     }
 }
 
@@ -122,7 +221,7 @@ impl Text {
     }
 }
 
-impl<const LIMIT: usize> Display for Varchar<LIMIT> {
+impl<const LIMIT: usize, P> Display for Varchar<LIMIT, P> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&self.0)
     }
@@ -134,18 +233,40 @@ impl Display for Text {
     }
 }
 
-impl<'a, const LIMIT: usize> TryFrom<SqlValue<'a>> for Varchar<LIMIT> {
+impl<'a, const LIMIT: usize, P: VarcharPolicy> TryFrom<SqlValue<'a>> for Varchar<LIMIT, P> {
     type Error = ChinSqlError;
 
     fn try_from(value: SqlValue<'a>) -> Result<Self, Self::Error> {
         match value {
-            SqlValue::Str(cow) => {
-                let s = cow.to_string();
-                Ok(s.try_into()?)
-            }
+            SqlValue::Str(cow) => Self::checked(cow),
             other => Err(ChinSqlError::TransformError(format!(
                 "Cannot transform {other:?} into Varchar"
             ))),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Bytes, Graphemes, Truncate, Varchar};
+
+    #[test]
+    fn varchar_policy() {
+        let name = "张三丰张三丰张三丰张三丰张三丰张三丰张三";
+        assert!(Varchar::<40, Bytes>::try_from(name.to_owned()).is_err());
+        assert!(Varchar::<40>::try_from(name.to_owned()).is_ok());
+
+        // 4 graphemes, 7 chars
+        let family = "a👨‍👩‍👧bc";
+        assert!(Varchar::<4>::try_from(family).is_err());
+        assert!(Varchar::<4, Graphemes>::try_from(family).is_ok());
+
+        let v: Varchar<5, Truncate<Bytes>> = name.try_into().unwrap();
+        assert_eq!("张", v.as_str());
+        assert_eq!("ab", Varchar::<10>::limit("ab").as_str());
+        assert_eq!("a👨‍👩‍👧", Varchar::<2, Graphemes>::limit(family).as_str());
+
+        let v: Result<Varchar<3>, _> = serde_json::from_str("\"张三丰张\"");
+        assert!(v.is_err());
+    }
+}
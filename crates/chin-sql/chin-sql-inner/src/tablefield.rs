@@ -4,7 +4,13 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use crate::{ILikeType, SqlBuilder, SqlValue, Wheres, str_type::Text};
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::{
+    DbType, ILikeType, JsonPath, LikeCase, OrderBy, SqlBuilder, SqlValue, Wheres,
+    str_type::{Text, Varchar},
+    time_type::TID,
+};
 
 pub trait SqlTable<'a> {
     fn table_expr(&self) -> SqlBuilder<'a>;
@@ -15,7 +21,19 @@ pub trait SqlTable<'a> {
 pub struct SqlField<'a> {
     pub alias: Option<&'a str>,
     pub table_alias: &'a str,
-    pub field_name: &'static str,
+    pub field_name: &'a str,
+    /// selected as `field_name`
+    pub json: Option<JsonPath<'a>>,
+}
+
+impl<'a> SqlField<'a> {
+    pub(crate) fn to_sql(&self, db_type: DbType) -> String {
+        match (&self.json, self.alias) {
+            (Some(json), _) => format!("{} as {}", json.to_sql(db_type), self.field_name),
+            (None, Some(alias)) => format!("{}.{} as {}", self.table_alias, self.field_name, alias),
+            (None, None) => format!("{}.{}", self.table_alias, self.field_name),
+        }
+    }
 }
 
 pub struct SqlTypedField<'a, T> {
@@ -23,6 +41,15 @@ pub struct SqlTypedField<'a, T> {
     value_type: PhantomData<T>,
 }
 
+impl<'a, T> Clone for SqlTypedField<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            field: self.field.clone(),
+            value_type: PhantomData,
+        }
+    }
+}
+
 impl<'a, T> Deref for SqlTypedField<'a, T> {
     type Target = SqlField<'a>;
 
@@ -37,12 +64,13 @@ impl<'a, T> DerefMut for SqlTypedField<'a, T> {
 }
 
 impl<'a, T> SqlTypedField<'a, T> {
-    pub fn new(table_alias: &'a str, field_name: &'static str) -> Self {
+    pub fn new(table_alias: &'a str, field_name: &'a str) -> Self {
         Self {
             field: SqlField {
                 alias: None,
                 table_alias,
                 field_name,
+                json: None,
             },
             value_type: PhantomData,
         }
@@ -72,9 +100,57 @@ impl<'a, T> SqlTypedField<'a, T> {
         format!("{}.{}", self.table_alias, self.field_name).into()
     }
 
+    pub fn json(&self) -> JsonPath<'a> {
+        JsonPath::new(self.twn())
+    }
+
     pub fn erased(&self) -> SqlField<'a> {
         self.field.clone()
     }
+
+    /// The same column seen from outside a sub query which is aliased as `table_alias`.
+    pub fn derived(&self, table_alias: &'a str) -> Self {
+        Self::new(table_alias, self.alias.unwrap_or(self.field_name))
+    }
+}
+
+/// Field types which can be compared with `>`, `<` and `between`.
+pub trait SqlOrdered {}
+
+/// Field types which can be matched with `like` and `ilike`.
+pub trait SqlLikeable {}
+
+macro_rules! sql_ordered {
+    ($($tp:ty),*) => {
+        $(impl SqlOrdered for $tp {})*
+    };
+}
+
+sql_ordered!(
+    i8,
+    i16,
+    i32,
+    i64,
+    f64,
+    DateTime<FixedOffset>,
+    DateTime<Utc>,
+    TID
+);
+
+impl<T: SqlOrdered> SqlOrdered for Option<T> {}
+
+impl SqlLikeable for Text {}
+impl<const LIMIT: usize, P> SqlLikeable for Varchar<LIMIT, P> {}
+impl<T: SqlLikeable> SqlLikeable for Option<T> {}
+
+impl<'a, T> SqlTypedField<'a, T> {
+    pub fn asc(&self) -> OrderBy<'a> {
+        OrderBy::Asc(self.twn())
+    }
+
+    pub fn desc(&self) -> OrderBy<'a> {
+        OrderBy::Desc(self.twn())
+    }
 }
 
 impl<'a, T: 'a> SqlTypedField<'a, T>
@@ -85,31 +161,109 @@ where
         Wheres::equal(self.twn(), v.into())
     }
 
+    pub fn v_ne<V: Into<T>>(&self, v: V) -> Wheres<'a> {
+        Wheres::compare(self.twn(), "<>", v.into())
+    }
+
+    /// Compares with a slot of a [`crate::SqlTemplate`].
+    pub fn slot_cmp<S: Into<Cow<'a, str>>>(&self, operator: &'a str, name: S) -> Wheres<'a> {
+        Wheres::compare(self.twn(), operator, SqlValue::slot(name))
+    }
+
     pub fn v_in<V: Into<T>>(&self, vs: Vec<V>) -> Wheres<'a> {
         Wheres::r#in(self.twn(), vs.into_iter().map(|v| v.into()).collect())
     }
+
+    pub fn v_not_in<V: Into<T>>(&self, vs: Vec<V>) -> Wheres<'a> {
+        Wheres::not_in(self.twn(), vs.into_iter().map(|v| v.into()).collect())
+    }
 }
 
-impl<'a> SqlTypedField<'a, Text> {
+impl<'a, T> SqlTypedField<'a, Option<T>> {
+    pub fn is_null(&self) -> Wheres<'a> {
+        Wheres::is_null(self.twn())
+    }
+
+    pub fn is_not_null(&self) -> Wheres<'a> {
+        Wheres::is_not_null(self.twn())
+    }
+}
+
+impl<'a, T: SqlLikeable> SqlTypedField<'a, T> {
+    pub fn v_like<V: AsRef<str>>(&self, v: V, exact: ILikeType) -> Wheres<'a> {
+        Wheres::like(self.twn(), v.as_ref(), exact)
+    }
+
     pub fn v_ilike<V: AsRef<str>>(&self, v: V, exact: ILikeType) -> Wheres<'a> {
         Wheres::ilike(self.twn(), v.as_ref(), exact)
     }
+
+    /// See [`LikeCase::Unicode`].
+    pub fn v_ilike_unicode<V: AsRef<str>>(&self, v: V, exact: ILikeType) -> Wheres<'a> {
+        Wheres::like_case(self.twn(), v.as_ref(), exact, LikeCase::Unicode)
+    }
 }
 
-impl<'a> SqlTypedField<'a, i64> {
-    pub fn v_gt<V: Into<i64>>(&self, v: V) -> Wheres<'a> {
+impl<'a, T: 'a> SqlTypedField<'a, T>
+where
+    T: SqlOrdered + Into<SqlValue<'a>>,
+{
+    pub fn v_gt<V: Into<T>>(&self, v: V) -> Wheres<'a> {
         Wheres::compare(self.twn(), ">", v.into())
     }
 
-    pub fn v_lt<V: Into<i64>>(&self, v: V) -> Wheres<'a> {
+    pub fn v_lt<V: Into<T>>(&self, v: V) -> Wheres<'a> {
         Wheres::compare(self.twn(), "<", v.into())
     }
 
-    pub fn v_ge<V: Into<i64>>(&self, v: V) -> Wheres<'a> {
+    pub fn v_ge<V: Into<T>>(&self, v: V) -> Wheres<'a> {
         Wheres::compare(self.twn(), ">=", v.into())
     }
 
-    pub fn v_le<V: Into<i64>>(&self, v: V) -> Wheres<'a> {
+    pub fn v_le<V: Into<T>>(&self, v: V) -> Wheres<'a> {
         Wheres::compare(self.twn(), "<=", v.into())
     }
+
+    pub fn v_between<V: Into<T>>(&self, low: V, high: V) -> Wheres<'a> {
+        Wheres::between(self.twn(), low.into(), high.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DbType, Froms, IntoSqlSeg, SqlReader, SqlTypedField, Wheres, str_type::Varchar,
+        time_type::TID,
+    };
+
+    #[test]
+    fn typed_operators() {
+        let id: SqlTypedField<TID> = SqlTypedField::new("t", "id");
+        let name: SqlTypedField<Varchar<20>> = SqlTypedField::new("t", "name");
+        let age: SqlTypedField<Option<i32>> = SqlTypedField::new("t", "age");
+
+        let reader = SqlReader::builder(
+            vec![id.erased(), name.erased()],
+            Froms::Table {
+                table_name: "user",
+                alias: "t",
+            },
+        )
+        .wheres(Wheres::and([
+            id.v_gt(TID::never()),
+            name.v_like("ab", crate::ILikeType::RightFuzzy),
+            age.v_between(18, 30),
+            age.is_not_null(),
+            id.v_not_in(vec![TID::never()]),
+        ]))
+        .order_by([age.desc().nulls_last(), id.asc()])
+        .build();
+
+        let seg = reader.into_sql_seg(DbType::Postgres).unwrap();
+        assert_eq!(
+            "select t.id, t.name from user as t  where t.id > $1 and t.name like $2 escape '\\' and t.age between $3 and $4 and  t.age is not null  and  not ( t.id in ($5)) order by t.age desc nulls last, t.id asc ",
+            seg.seg
+        );
+        assert_eq!(5, seg.values.len());
+    }
 }
@@ -0,0 +1,408 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, RwLock},
+};
+
+use crate::{ChinSqlError, Froms, JoinCond, JoinType, SqlValue, Wheres};
+
+static TENANT_TABLES: LazyLock<RwLock<HashMap<&'static str, &'static [&'static str]>>> =
+    LazyLock::new(Default::default);
+
+/// Marks `table` as tenant scoped, building a query on it without a [`TenantScope`] fails.
+/// The derived builders of `gts_tenant` tables check without it, their `register_tenant`
+/// also covers readers and joins written by hand.
+pub fn register_tenant_table(table: &'static str, columns: &'static [&'static str]) {
+    TENANT_TABLES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(table, columns);
+}
+
+pub fn tenant_columns(table: &str) -> Option<&'static [&'static str]> {
+    TENANT_TABLES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(table)
+        .copied()
+}
+
+/// Column → value pairs, e.g. `tenant_id = 7`, which are ANDed into every query on a tenant
+/// scoped table and set on its inserts.
+#[derive(Clone, Debug, Default)]
+pub struct TenantScope<'a> {
+    values: Vec<(&'a str, SqlValue<'a>)>,
+}
+
+impl<'a> TenantScope<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn column<T: Into<SqlValue<'a>>>(mut self, column: &'a str, value: T) -> Self {
+        self.values.retain(|(c, _)| *c != column);
+        self.values.push((column, value.into()));
+        self
+    }
+
+    fn value(&self, table: &str, column: &str) -> Result<&SqlValue<'a>, ChinSqlError> {
+        self.values
+            .iter()
+            .find(|(c, _)| *c == column)
+            .map(|(_, v)| v)
+            .ok_or_else(|| missing(table, Some(column)))
+    }
+
+    /// `alias.column = value` for the tenant columns of `table`, nothing if it is not scoped.
+    pub fn wheres(&self, table: &str, alias: Option<&str>) -> Result<Wheres<'a>, ChinSqlError> {
+        self.columns_wheres(table, tenant_columns(table).unwrap_or_default(), alias)
+    }
+
+    /// Like [`TenantScope::wheres`] for the given tenant `columns` of `table`, e.g. the
+    /// `TENANT_COLUMNS` of a derived table.
+    pub fn columns_wheres(
+        &self,
+        table: &str,
+        columns: &[&str],
+        alias: Option<&str>,
+    ) -> Result<Wheres<'a>, ChinSqlError> {
+        let mut wheres = columns
+            .iter()
+            .map(|c| {
+                let key = match alias {
+                    Some(alias) => format!("{alias}.{c}"),
+                    None => c.to_string(),
+                };
+                Ok(Wheres::equal(key, self.value(table, c)?.clone()))
+            })
+            .collect::<Result<Vec<_>, ChinSqlError>>()?;
+        Ok(match wheres.len() {
+            0 => Wheres::None,
+            1 => wheres.remove(0),
+            _ => Wheres::and(wheres),
+        })
+    }
+
+    /// ANDs the scope of `table` into a condition on it, e.g. a derived `*_cond`.
+    pub fn cond(
+        &self,
+        table: &str,
+        alias: Option<&str>,
+        cond: Wheres<'a>,
+    ) -> Result<Wheres<'a>, ChinSqlError> {
+        Ok(Wheres::and([cond, self.wheres(table, alias)?]))
+    }
+}
+
+fn missing(table: &str, column: Option<&str>) -> ChinSqlError {
+    match column {
+        Some(column) => ChinSqlError::MissingTenantScope(format!("{table}.{column}")),
+        None => ChinSqlError::MissingTenantScope(table.to_owned()),
+    }
+}
+
+/// The tenant columns of `table`, `columns` set on a builder win over the registered ones.
+pub(crate) fn table_columns(
+    table: &str,
+    columns: Option<&'static [&'static str]>,
+) -> Option<&'static [&'static str]> {
+    columns.or_else(|| tenant_columns(table))
+}
+
+/// Predicates of a scoped `table`, an error without a scope.
+pub(crate) fn scope_table<'a>(
+    scope: Option<&TenantScope<'a>>,
+    table: &str,
+    columns: Option<&'static [&'static str]>,
+    alias: Option<&str>,
+) -> Result<Wheres<'a>, ChinSqlError> {
+    match (scope, table_columns(table, columns)) {
+        (Some(scope), Some(columns)) => scope.columns_wheres(table, columns, alias),
+        (None, Some(_)) => Err(missing(table, None)),
+        (_, None) => Ok(Wheres::None),
+    }
+}
+
+/// The tenant columns of a scoped `table` with their values, an error without a scope.
+pub(crate) fn scope_values<'a>(
+    scope: Option<&TenantScope<'a>>,
+    table: &str,
+    columns: Option<&'static [&'static str]>,
+) -> Result<Vec<(&'static str, SqlValue<'a>)>, ChinSqlError> {
+    let Some(columns) = table_columns(table, columns) else {
+        return Ok(vec![]);
+    };
+    let scope = scope.ok_or_else(|| missing(table, None))?;
+    columns
+        .iter()
+        .map(|c| Ok((*c, scope.value(table, c)?.clone())))
+        .collect()
+}
+
+/// An explicit value of a tenant column must be the one of the scope, integers of any
+/// width compare by value, e.g. a derived `i64` column and a scope of `7`.
+pub(crate) fn check_value(
+    table: &str,
+    column: &str,
+    value: &SqlValue<'_>,
+    scoped: &SqlValue<'_>,
+) -> Result<(), ChinSqlError> {
+    fn int(v: &SqlValue<'_>) -> Option<i64> {
+        match v {
+            SqlValue::I8(v) => Some(*v as i64),
+            SqlValue::I16(v) => Some(*v as i64),
+            SqlValue::I32(v) => Some(*v as i64),
+            SqlValue::I64(v) => Some(*v),
+            _ => None,
+        }
+    }
+    let same = match (value, scoped) {
+        (SqlValue::Str(a), SqlValue::Str(b)) => a == b,
+        (SqlValue::Bool(a), SqlValue::Bool(b)) => a == b,
+        (SqlValue::Blob(a), SqlValue::Blob(b)) => a == b,
+        (a, b) => int(a).is_some() && int(a) == int(b),
+    };
+    match same {
+        true => Ok(()),
+        false => Err(ChinSqlError::BuilderSqlError(format!(
+            "{table}.{column} is {value:?} but the tenant scope is {scoped:?}"
+        ))),
+    }
+}
+
+/// `wheres` and the predicates of the scope, `wheres` as is when nothing is scoped.
+pub(crate) fn and_scoped<'a>(wheres: Wheres<'a>, mut scoped: Vec<Wheres<'a>>) -> Wheres<'a> {
+    scoped.retain(|w| !w.empty());
+    if scoped.is_empty() {
+        return wheres;
+    }
+    scoped.insert(0, wheres);
+    Wheres::and(scoped)
+}
+
+/// Pushes the predicates of the tables in `froms` to `wheres`. Outer joined tables are
+/// scoped in their `on` conditions, sub queries inherit the scope and check themselves.
+pub(crate) fn scope_froms<'a>(
+    froms: &mut Froms<'a>,
+    scope: Option<&TenantScope<'a>>,
+    wheres: &mut Vec<Wheres<'a>>,
+) -> Result<(), ChinSqlError> {
+    match froms {
+        Froms::Table { table_name, alias } => {
+            wheres.push(scope_table(scope, table_name, None, Some(alias))?);
+        }
+        Froms::SubQuery { table, .. } => table.inherit_scope(scope),
+        Froms::Union { table, .. } => table.iter_mut().for_each(|t| t.inherit_scope(scope)),
+        Froms::Joins(joins) => {
+            scope_froms(&mut joins.base, scope, wheres)?;
+            for join in joins.joins.iter_mut() {
+                let outer = matches!(
+                    join.join_type,
+                    JoinType::LeftJoin | JoinType::RightJoin | JoinType::FullJoin
+                );
+                match &join.table {
+                    Froms::Table { table_name, alias } if outer => {
+                        let cond = scope_table(scope, table_name, None, Some(alias))?;
+                        if cond.empty() {
+                            continue;
+                        }
+                        if !join.using.is_empty() {
+                            return Err(ChinSqlError::BuilderSqlError(format!(
+                                "outer join of tenant scoped {table_name} needs on conditions"
+                            )));
+                        }
+                        join.conds.push(JoinCond::Wheres(cond));
+                    }
+                    _ => scope_froms(&mut join.table, scope, wheres)?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ChinSqlError, DbType, Froms, IntoSqlSeg, JoinTable, JoinType, Joins, SqlBuilder,
+        SqlDeleter, SqlInserter, SqlReader, SqlTypedField, SqlUpdater, SqlValue, Wheres,
+    };
+
+    use super::{TenantScope, register_tenant_table};
+
+    #[test]
+    fn tenant_scope() {
+        register_tenant_table("tenant_order", &["tenant_id"]);
+        register_tenant_table("tenant_item", &["tenant_id"]);
+        let scope = TenantScope::new().column("tenant_id", 7);
+
+        let id = SqlTypedField::<i64>::new("o", "id");
+        let reader = || {
+            SqlReader::builder(
+                vec![id.erased()],
+                Froms::from(
+                    Joins::new(Froms::Table {
+                        table_name: "tenant_order",
+                        alias: "o",
+                    })
+                    .join(
+                        JoinTable::new(
+                            JoinType::LeftJoin,
+                            Froms::Table {
+                                table_name: "tenant_item",
+                                alias: "i",
+                            },
+                        )
+                        .on(Wheres::Raw("i.order_id = o.id".into())),
+                    )
+                    .join(
+                        JoinTable::new(
+                            JoinType::InnerJoin,
+                            Froms::Table {
+                                table_name: "customer",
+                                alias: "c",
+                            },
+                        )
+                        .on(Wheres::Raw("c.id = o.customer_id".into())),
+                    ),
+                ),
+            )
+            .wheres(id.v_gt(1))
+        };
+        assert!(matches!(
+            reader().build().into_sql_seg(DbType::Postgres),
+            Err(ChinSqlError::MissingTenantScope(t)) if t == "tenant_order"
+        ));
+        let seg = reader()
+            .scope(&scope)
+            .build()
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            "select o.id from tenant_order as o left join tenant_item as i  on  i.order_id = o.id  and i.tenant_id = $1 inner join customer as c  on  c.id = o.customer_id  where o.id > $2 and o.tenant_id = $3 ",
+            seg.seg
+        );
+
+        // sub queries inherit the scope of the outer reader
+        let sub = SqlReader::builder(
+            vec![id.erased()],
+            Froms::SubQuery {
+                table: Box::new(
+                    SqlReader::builder(
+                        vec![id.erased()],
+                        Froms::Table {
+                            table_name: "tenant_order",
+                            alias: "o",
+                        },
+                    )
+                    .build(),
+                ),
+                alias: "o",
+            },
+        )
+        .scope(&scope)
+        .build();
+        assert_eq!(
+            "select o.id from ( select o.id from tenant_order as o  where o.tenant_id = ? ) as o ",
+            sub.into_sql_seg(DbType::Sqlite).unwrap().seg
+        );
+
+        let seg = SqlUpdater::new("tenant_order")
+            .set("state", 1)
+            .r#where(Wheres::equal("id", 3))
+            .scope(&scope)
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            " update tenant_order set  state = $1  where id = $2 and tenant_order.tenant_id = $3",
+            seg.seg
+        );
+        assert!(
+            SqlUpdater::new("tenant_order")
+                .set("state", 1)
+                .r#where(Wheres::equal("id", 3))
+                .into_sql_seg(DbType::Postgres)
+                .is_err()
+        );
+
+        let seg = SqlDeleter::new("tenant_order")
+            .r#where(Wheres::equal("id", 3))
+            .scope(&scope)
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            "delete from tenant_order where id = $1 and tenant_order.tenant_id = $2",
+            seg.seg
+        );
+        // the scope is no filter of its own
+        assert!(
+            SqlDeleter::new("tenant_order")
+                .scope(&scope)
+                .into_sql_seg(DbType::Postgres)
+                .is_err()
+        );
+
+        let seg = SqlInserter::new("tenant_order")
+            .field("id", 3)
+            .scope(&scope)
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!(
+            "insert  into tenant_order(id,tenant_id) values ($1, $2)",
+            seg.seg
+        );
+        assert!(matches!(seg.values[1], SqlValue::I32(7)));
+        // explicit values of another tenant are an error, not overwritten
+        let explicit = |tenant: i64| {
+            SqlInserter::new("tenant_order")
+                .field("id", 3)
+                .field("tenant_id", tenant)
+                .scope(&scope)
+                .into_sql_seg(DbType::Postgres)
+        };
+        assert!(explicit(7).is_ok());
+        assert!(explicit(8).is_err());
+        assert!(
+            SqlInserter::rows(
+                "tenant_order",
+                vec!["id", "tenant_id"],
+                vec![vec![SqlValue::I32(1), SqlValue::I32(8)]],
+            )
+            .scope(&scope)
+            .into_sql_seg(DbType::Postgres)
+            .is_err()
+        );
+        let seg = SqlInserter::rows(
+            "tenant_order",
+            vec!["id"],
+            vec![vec![SqlValue::I32(1)], vec![SqlValue::I32(2)]],
+        )
+        .scope(&scope)
+        .into_sql_seg(DbType::Postgres)
+        .unwrap();
+        assert_eq!(
+            "insert  into tenant_order(id,tenant_id) values ($1, $2), ($3, $4)",
+            seg.seg
+        );
+
+        let seg = SqlBuilder::read_all("tenant_order")
+            .r#where(Wheres::equal("id", 3))
+            .scope(&scope)
+            .into_sql_seg(DbType::Sqlite)
+            .unwrap();
+        assert_eq!(
+            "select * from tenant_order  where id = ? and tenant_order.tenant_id = ? ",
+            seg.seg
+        );
+        assert!(
+            SqlBuilder::read_all("tenant_order")
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+        assert!(
+            SqlBuilder::read_all("tenant_order")
+                .scope(&TenantScope::new().column("org_id", 1))
+                .into_sql_seg(DbType::Sqlite)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,784 @@
+use std::{
+    borrow::Cow,
+    io::{BufRead, Write},
+    sync::Arc,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, FixedOffset, Utc};
+use thiserror::Error;
+
+use crate::{
+    ChinSqlError, CreateTableField, CreateTableSql, LimitOffset, LogicFieldType, OnConflict,
+    SegOrVal, SqlBuilder, SqlExecutor, SqlInserter, SqlValue, SqlValueRow, SqlValueStatic,
+    TenantScope, Wheres,
+};
+
+// the smallest bind limit, sqlite since 3.32.0
+const MAX_BINDS: usize = 32766;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferFormat {
+    /// With a header line, an empty field is null unless the column is a not null text.
+    Csv,
+    /// One json object per line.
+    JsonLines,
+}
+
+#[derive(Error, Debug)]
+pub enum TransferError {
+    #[error("io error {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {message}")]
+    Format { line: u64, message: String },
+    #[error(transparent)]
+    Sql(#[from] ChinSqlError),
+}
+
+/// A row which could not be imported, the other rows are still imported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    /// rows reported as inserted by the database
+    pub imported: usize,
+    pub failed: Vec<RowError>,
+}
+
+/// Moves the rows of a table from and to files, values are coerced to the
+/// [`LogicFieldType`]s of the table.
+///
+/// Timestamps are written as RFC 3339 and blobs as base64, both formats read them back.
+#[derive(Clone, Debug)]
+pub struct TableTransfer {
+    pub table: &'static CreateTableSql,
+    pub format: TransferFormat,
+    pub batch_size: usize,
+    pub on_conflict: OnConflict,
+    /// Needed for tenant tables, the export reads and the import writes only this tenant.
+    pub scope: Option<TenantScope<'static>>,
+}
+
+impl TableTransfer {
+    pub fn new(table: &'static CreateTableSql, format: TransferFormat) -> Self {
+        Self {
+            table,
+            format,
+            batch_size: 500,
+            on_conflict: OnConflict::Default,
+            scope: None,
+        }
+    }
+
+    pub fn batch_size(self, batch_size: usize) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    pub fn on_conflict(self, on_conflict: OnConflict) -> Self {
+        Self {
+            on_conflict,
+            ..self
+        }
+    }
+
+    pub fn scope(self, scope: &TenantScope<'static>) -> Self {
+        Self {
+            scope: Some(scope.clone()),
+            ..self
+        }
+    }
+
+    /// Reads the table page by page in primary key order, returns the number of rows.
+    ///
+    /// Each page starts after the primary key of the last row of the previous one, so rows
+    /// inserted or deleted meanwhile do not shift the pages. Tables without a primary key are
+    /// paged by offset, ordered by all columns.
+    pub async fn export<E: SqlExecutor, W: Write>(
+        &self,
+        executor: &E,
+        writer: W,
+    ) -> Result<usize, TransferError> {
+        let fields = self.table.all_fields();
+        let columns: Arc<[Arc<str>]> = fields.iter().map(|f| Arc::from(*f)).collect();
+        let order = if self.table.pkey.is_empty() {
+            fields.join(", ")
+        } else {
+            self.table.pkey.join(", ")
+        };
+        let pkey: Vec<usize> = self
+            .table
+            .pkey
+            .iter()
+            .filter_map(|key| fields.iter().position(|f| f == key))
+            .collect();
+        let batch_size = self.batch_size.max(1);
+
+        let mut sink = match self.format {
+            TransferFormat::Csv => {
+                let mut csv = csv::Writer::from_writer(writer);
+                csv.write_record(&fields).map_err(csv_error)?;
+                Sink::Csv(Box::new(csv))
+            }
+            TransferFormat::JsonLines => Sink::JsonLines(writer),
+        };
+        let mut count = 0;
+        let mut last: Option<Vec<SqlValueStatic>> = None;
+        loop {
+            let mut page = SqlBuilder::read(self.table.table_name, &fields);
+            if let Some(last) = last.take() {
+                page = page.r#where(after(&order, last));
+            }
+            if let Some(scope) = &self.scope {
+                page = page.scope(scope);
+            }
+            let page = page.seg("order by").seg(order.clone());
+            let page = if pkey.is_empty() {
+                page.limit_offset(LimitOffset::new(batch_size).offset(count))
+            } else {
+                page.limit(batch_size)
+            };
+            let rows = executor.query(page).await?;
+            for row in &rows {
+                let values = self
+                    .table
+                    .fields
+                    .iter()
+                    .zip(row.values())
+                    .map(|(field, value)| coerce(field.kind, value.clone()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match &mut sink {
+                    Sink::Csv(csv) => csv
+                        .write_record(values.iter().map(csv_text))
+                        .map_err(csv_error)?,
+                    Sink::JsonLines(writer) => {
+                        let row = SqlValueRow::new(columns.clone(), values);
+                        serde_json::to_writer(&mut *writer, &row)
+                            .map_err(|err| TransferError::Io(err.into()))?;
+                        writer.write_all(b"\n")?;
+                    }
+                }
+            }
+            count += rows.len();
+            if rows.len() < batch_size {
+                break;
+            }
+            if let Some(row) = rows.last() {
+                last = Some(pkey.iter().map(|&i| row.values()[i].clone()).collect());
+            }
+        }
+        match &mut sink {
+            Sink::Csv(csv) => csv.flush()?,
+            Sink::JsonLines(writer) => writer.flush()?,
+        }
+        Ok(count)
+    }
+
+    /// Inserts the rows in batches, rows which can not be parsed are reported and skipped.
+    ///
+    /// A batch the database rejects is inserted again row by row, the rows which still fail
+    /// are reported with the error of the database. Where the executor takes savepoints, e.g.
+    /// in a transaction, every insert runs in one, so a rejected batch does not abort the
+    /// transaction as it does on postgres.
+    pub async fn import<E: SqlExecutor, R: BufRead>(
+        &self,
+        executor: &E,
+        reader: R,
+    ) -> Result<ImportReport, TransferError> {
+        let savepoints = executor.execute("savepoint chin_transfer").await.is_ok()
+            && executor
+                .execute("release savepoint chin_transfer")
+                .await
+                .is_ok();
+        let mut batch = Batch {
+            transfer: self,
+            savepoints,
+            report: ImportReport::default(),
+            columns: vec![],
+            rows: vec![],
+        };
+
+        match self.format {
+            TransferFormat::Csv => {
+                let mut csv = csv::Reader::from_reader(reader);
+                let fields = csv
+                    .headers()
+                    .map_err(csv_error)?
+                    .iter()
+                    .map(|name| {
+                        self.field(name).ok_or_else(|| TransferError::Format {
+                            line: 1,
+                            message: format!("unknown column {name}"),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                batch.columns = fields.iter().map(|f| f.name).collect();
+
+                for record in csv.records() {
+                    let record = match record {
+                        Ok(record) => record,
+                        Err(err) => {
+                            let line = err.position().map(|p| p.line()).unwrap_or_default();
+                            batch.fail(line, err.to_string());
+                            continue;
+                        }
+                    };
+                    let line = record.position().map(|p| p.line()).unwrap_or_default();
+                    let row = fields
+                        .iter()
+                        .zip(record.iter())
+                        .map(|(field, text)| parse_csv(field, text))
+                        .collect::<Result<Vec<_>, _>>();
+                    batch.push(executor, line, row).await?;
+                }
+            }
+            TransferFormat::JsonLines => {
+                batch.columns = self.table.all_fields();
+                for (n, line) in reader.lines().enumerate() {
+                    let line_no = n as u64 + 1;
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let row = self.parse_json(&line);
+                    batch.push(executor, line_no, row).await?;
+                }
+            }
+        }
+        batch.flush(executor).await?;
+        Ok(batch.report)
+    }
+
+    fn field(&self, name: &str) -> Option<&'static CreateTableField> {
+        self.table.fields.iter().find(|f| f.name == name)
+    }
+
+    fn parse_json(&self, line: &str) -> Result<Vec<SqlValueStatic>, String> {
+        let mut object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(line).map_err(|err| err.to_string())?;
+        let row = self
+            .table
+            .fields
+            .iter()
+            .map(|field| match object.remove(field.name) {
+                Some(value) => parse_json_value(field, value),
+                None => null_value(field),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match object.keys().next() {
+            Some(name) => Err(format!("unknown column {name}")),
+            None => Ok(row),
+        }
+    }
+}
+
+/// `(a, b) > (?, ?)`, the rows after `last` in the order of the key columns.
+fn after(key: &str, last: Vec<SqlValueStatic>) -> Wheres<'static> {
+    let mut sov = vec![SegOrVal::Str(format!("({key}) > (").into())];
+    for (n, value) in last.into_iter().enumerate() {
+        if n > 0 {
+            sov.push(SegOrVal::Str(", ".into()));
+        }
+        sov.push(SegOrVal::Val(value));
+    }
+    sov.push(SegOrVal::Str(")".into()));
+    Wheres::SOV(sov)
+}
+
+enum Sink<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    JsonLines(W),
+}
+
+struct Batch<'t> {
+    transfer: &'t TableTransfer,
+    savepoints: bool,
+    report: ImportReport,
+    columns: Vec<&'static str>,
+    rows: Vec<(u64, Vec<SqlValueStatic>)>,
+}
+
+impl<'t> Batch<'t> {
+    fn fail(&mut self, line: u64, message: String) {
+        self.report.failed.push(RowError { line, message });
+    }
+
+    async fn push<E: SqlExecutor>(
+        &mut self,
+        executor: &E,
+        line: u64,
+        row: Result<Vec<SqlValueStatic>, String>,
+    ) -> Result<(), TransferError> {
+        match row {
+            Ok(row) => self.rows.push((line, row)),
+            Err(message) => self.fail(line, message),
+        }
+        let limit = (MAX_BINDS / self.columns.len().max(1))
+            .min(self.transfer.batch_size)
+            .max(1);
+        if self.rows.len() >= limit {
+            self.flush(executor).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush<E: SqlExecutor>(&mut self, executor: &E) -> Result<(), TransferError> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let rows: Vec<(u64, Vec<SqlValueStatic>)> = self.rows.drain(..).collect();
+        let batch = rows.iter().map(|(_, row)| row.clone()).collect();
+        match self.insert(executor, batch).await {
+            Ok(imported) => self.report.imported += imported,
+            Err(_) => {
+                for (line, row) in rows {
+                    match self.insert(executor, vec![row]).await {
+                        Ok(imported) => self.report.imported += imported,
+                        Err(err) => self.fail(line, err.to_string()),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn insert<E: SqlExecutor>(
+        &self,
+        executor: &E,
+        rows: Vec<Vec<SqlValueStatic>>,
+    ) -> Result<usize, ChinSqlError> {
+        if !self.savepoints {
+            return executor.execute(self.inserter(rows)).await;
+        }
+        executor.execute("savepoint chin_transfer").await?;
+        let imported = executor.execute(self.inserter(rows)).await;
+        if imported.is_err() {
+            executor
+                .execute("rollback to savepoint chin_transfer")
+                .await?;
+        }
+        executor.execute("release savepoint chin_transfer").await?;
+        imported
+    }
+
+    fn inserter(&self, rows: Vec<Vec<SqlValueStatic>>) -> SqlInserter<'static> {
+        let inserter =
+            SqlInserter::rows(self.transfer.table.table_name, self.columns.clone(), rows)
+                .on_conflict(self.transfer.on_conflict.clone());
+        match &self.transfer.scope {
+            Some(scope) => inserter.scope(scope),
+            None => inserter,
+        }
+    }
+}
+
+fn csv_error(err: csv::Error) -> TransferError {
+    let line = err.position().map(|p| p.line()).unwrap_or_default();
+    match err.into_kind() {
+        csv::ErrorKind::Io(err) => TransferError::Io(err),
+        kind => TransferError::Format {
+            line,
+            message: format!("{kind:?}"),
+        },
+    }
+}
+
+/// Converts what the database returned to the value of the declared type, e.g. the
+/// integers which sqlite stores for bools and timestamps.
+fn coerce(kind: LogicFieldType, value: SqlValueStatic) -> Result<SqlValueStatic, ChinSqlError> {
+    if matches!(value, SqlValue::Null(_) | SqlValue::NullUnknown) {
+        return Ok(SqlValue::Null(kind));
+    }
+    Ok(match kind {
+        LogicFieldType::Bool => SqlValue::Bool(value.try_into()?),
+        LogicFieldType::I8 => SqlValue::I8(value.try_into()?),
+        LogicFieldType::I16 => SqlValue::I16(value.try_into()?),
+        LogicFieldType::I32 => SqlValue::I32(value.try_into()?),
+        LogicFieldType::I64 => SqlValue::I64(value.try_into()?),
+        LogicFieldType::F64 => match value {
+            SqlValue::I64(v) => SqlValue::F64(v as f64),
+            value => SqlValue::F64(value.try_into()?),
+        },
+        LogicFieldType::Varchar(..) | LogicFieldType::Text => {
+            SqlValue::Str(Cow::Owned(value.try_into()?))
+        }
+        LogicFieldType::Blob => match value {
+            SqlValue::Blob(v) => SqlValue::Blob(v),
+            value => {
+                return Err(ChinSqlError::TransformError(format!(
+                    "{value:?} is not a blob"
+                )));
+            }
+        },
+        LogicFieldType::Timestamptz => SqlValue::FixedOffset(value.try_into()?),
+        LogicFieldType::Timestamp => SqlValue::Utc(value.try_into()?),
+    })
+}
+
+fn csv_text(value: &SqlValueStatic) -> String {
+    match value {
+        SqlValue::Bool(v) => v.to_string(),
+        SqlValue::I8(v) => v.to_string(),
+        SqlValue::I16(v) => v.to_string(),
+        SqlValue::I32(v) => v.to_string(),
+        SqlValue::I64(v) => v.to_string(),
+        SqlValue::F64(v) => v.to_string(),
+        SqlValue::Str(v) => v.to_string(),
+        SqlValue::FixedOffset(v) => v.to_rfc3339(),
+        SqlValue::Utc(v) => v.to_rfc3339(),
+        SqlValue::Blob(v) => STANDARD.encode(v),
+        SqlValue::Null(_) | SqlValue::NullUnknown | SqlValue::Slot(_) => String::new(),
+    }
+}
+
+fn null_value(field: &CreateTableField) -> Result<SqlValueStatic, String> {
+    if field.not_null {
+        Err(format!("{} can not be null", field.name))
+    } else {
+        Ok(SqlValue::Null(field.kind))
+    }
+}
+
+fn parse_csv(field: &CreateTableField, text: &str) -> Result<SqlValueStatic, String> {
+    let text_column = matches!(
+        field.kind,
+        LogicFieldType::Text | LogicFieldType::Varchar(..)
+    );
+    if text.is_empty() && !(text_column && field.not_null) {
+        return null_value(field);
+    }
+    parse_str(field, text)
+}
+
+fn parse_int(field: &CreateTableField, v: i64) -> Result<SqlValueStatic, String> {
+    let out_of_range = |_| format!("{v} is out of range of {}", field.name);
+    Ok(match field.kind {
+        LogicFieldType::I8 => SqlValue::I8(v.try_into().map_err(out_of_range)?),
+        LogicFieldType::I16 => SqlValue::I16(v.try_into().map_err(out_of_range)?),
+        LogicFieldType::I32 => SqlValue::I32(v.try_into().map_err(out_of_range)?),
+        LogicFieldType::F64 => SqlValue::F64(v as f64),
+        _ => SqlValue::I64(v),
+    })
+}
+
+fn parse_str(field: &CreateTableField, text: &str) -> Result<SqlValueStatic, String> {
+    let invalid = || {
+        format!(
+            "{text:?} is not a valid {:?} for {}",
+            field.kind, field.name
+        )
+    };
+    match field.kind {
+        LogicFieldType::Bool => match text {
+            "true" | "1" => Ok(SqlValue::Bool(true)),
+            "false" | "0" => Ok(SqlValue::Bool(false)),
+            _ => Err(invalid()),
+        },
+        LogicFieldType::I8 | LogicFieldType::I16 | LogicFieldType::I32 | LogicFieldType::I64 => {
+            parse_int(field, text.parse().map_err(|_| invalid())?)
+        }
+        LogicFieldType::F64 => Ok(SqlValue::F64(text.parse().map_err(|_| invalid())?)),
+        LogicFieldType::Varchar(..) | LogicFieldType::Text => {
+            Ok(SqlValue::Str(Cow::Owned(text.to_owned())))
+        }
+        LogicFieldType::Blob => Ok(SqlValue::Blob(Cow::Owned(
+            STANDARD.decode(text).map_err(|_| invalid())?,
+        ))),
+        LogicFieldType::Timestamptz => Ok(SqlValue::FixedOffset(
+            DateTime::<FixedOffset>::parse_from_rfc3339(text).map_err(|_| invalid())?,
+        )),
+        LogicFieldType::Timestamp => Ok(SqlValue::Utc(
+            DateTime::<FixedOffset>::parse_from_rfc3339(text)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+        )),
+    }
+}
+
+fn parse_json_value(
+    field: &CreateTableField,
+    value: serde_json::Value,
+) -> Result<SqlValueStatic, String> {
+    use serde_json::Value;
+
+    match (field.kind, value) {
+        (_, Value::Null) => null_value(field),
+        (LogicFieldType::Bool, Value::Bool(v)) => Ok(SqlValue::Bool(v)),
+        (LogicFieldType::F64, Value::Number(v)) => v
+            .as_f64()
+            .map(SqlValue::F64)
+            .ok_or_else(|| format!("{v} is not a valid F64 for {}", field.name)),
+        (
+            LogicFieldType::I8 | LogicFieldType::I16 | LogicFieldType::I32 | LogicFieldType::I64,
+            Value::Number(v),
+        ) => match v.as_i64() {
+            Some(v) => parse_int(field, v),
+            None => Err(format!("{v} is not an integer for {}", field.name)),
+        },
+        (_, Value::String(s)) => parse_str(field, &s),
+        (kind, value) => Err(format!(
+            "{value} is not a valid {kind:?} for {}",
+            field.name
+        )),
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use chrono::DateTime;
+
+    use crate::{
+        CreateTableField, CreateTableSql, DbType, LogicFieldType, SqlExecutor, SqlInserter,
+        SqlValue, TenantScope, register_tenant_table, str_type::LengthUnit,
+    };
+
+    use super::{RowError, TableTransfer, TransferFormat};
+
+    const FIELDS: &[CreateTableField] = &[
+        CreateTableField {
+            name: "id",
+            kind: LogicFieldType::I64,
+            not_null: true,
+        },
+        CreateTableField {
+            name: "name",
+            kind: LogicFieldType::Varchar(16, LengthUnit::Chars),
+            not_null: true,
+        },
+        CreateTableField {
+            name: "vip",
+            kind: LogicFieldType::Bool,
+            not_null: true,
+        },
+        CreateTableField {
+            name: "score",
+            kind: LogicFieldType::F64,
+            not_null: false,
+        },
+        CreateTableField {
+            name: "at",
+            kind: LogicFieldType::Timestamptz,
+            not_null: true,
+        },
+        CreateTableField {
+            name: "data",
+            kind: LogicFieldType::Blob,
+            not_null: false,
+        },
+    ];
+
+    static MEMBER: CreateTableSql = CreateTableSql {
+        table_name: "member",
+        fields: FIELDS,
+        pkey: &["id"],
+        unikeys: &[],
+        keys: &[],
+        tenant: &[],
+    };
+
+    static MEMBER_COPY: CreateTableSql = CreateTableSql {
+        table_name: "member_copy",
+        fields: FIELDS,
+        pkey: &["id"],
+        unikeys: &[],
+        keys: &[],
+        tenant: &[],
+    };
+
+    #[tokio::test]
+    async fn export_import() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        for table in [&MEMBER, &MEMBER_COPY] {
+            for sql in table.to_owned_sql().sqls(DbType::Sqlite).unwrap() {
+                conn.execute(&sql, []).unwrap();
+            }
+        }
+        let at = DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z").unwrap();
+        let rows = vec![
+            vec![
+                SqlValue::I64(1),
+                "a, \"b\"".into(),
+                true.into(),
+                1.5.into(),
+                at.into(),
+                SqlValue::Blob(b"hi".into()),
+            ],
+            vec![
+                SqlValue::I64(2),
+                "".into(),
+                false.into(),
+                SqlValue::Null(LogicFieldType::F64),
+                at.into(),
+                SqlValue::Null(LogicFieldType::Blob),
+            ],
+        ];
+        let inserter = SqlInserter::rows("member", MEMBER.all_fields(), rows);
+        assert_eq!(2, SqlExecutor::execute(&conn, inserter).await.unwrap());
+
+        for format in [TransferFormat::Csv, TransferFormat::JsonLines] {
+            let mut out = vec![];
+            let exported = TableTransfer::new(&MEMBER, format)
+                .batch_size(2)
+                .export(&conn, &mut out)
+                .await
+                .unwrap();
+            assert_eq!(2, exported);
+
+            conn.execute("delete from member_copy", []).unwrap();
+            let report = TableTransfer::new(&MEMBER_COPY, format)
+                .batch_size(2)
+                .import(&conn, out.as_slice())
+                .await
+                .unwrap();
+            assert_eq!(2, report.imported);
+            assert!(report.failed.is_empty());
+
+            let mut again = vec![];
+            TableTransfer::new(&MEMBER_COPY, format)
+                .export(&conn, &mut again)
+                .await
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                String::from_utf8(again).unwrap()
+            );
+        }
+
+        let mut out = vec![];
+        TableTransfer::new(&MEMBER, TransferFormat::JsonLines)
+            .export(&conn, &mut out)
+            .await
+            .unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with(
+            r#"{"id":1,"name":"a, \"b\"","vip":true,"score":1.5,"at":"2024-05-01T00:00:00+00:00","data":"aGk="}"#
+        ));
+
+        conn.execute("delete from member_copy", []).unwrap();
+        let input = "id,name,vip,at\n1,a,true,2024-05-01T08:00:00+08:00\n2,b,maybe,2024-05-01T08:00:00+08:00\n3,c,0,\n";
+        let report = TableTransfer::new(&MEMBER_COPY, TransferFormat::Csv)
+            .import(&conn, input.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(1, report.imported);
+        assert_eq!(
+            vec![3, 4],
+            report.failed.iter().map(|e| e.line).collect::<Vec<_>>()
+        );
+
+        let input = "{\"id\": 9, \"name\": \"x\", \"vip\": false, \"at\": \"2024-05-01T00:00:00Z\"}\n\n{\"id\": 10, \"nope\": 1}\n";
+        let report = TableTransfer::new(&MEMBER_COPY, TransferFormat::JsonLines)
+            .import(&conn, input.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(1, report.imported);
+        assert_eq!(
+            vec![RowError {
+                line: 3,
+                message: "name can not be null".to_owned()
+            }],
+            report.failed
+        );
+
+        // the batch fails on the existing id 9, the other row is still imported, also in a
+        // transaction
+        for (in_tx, id) in [(false, 11), (true, 12)] {
+            if in_tx {
+                conn.execute("begin", []).unwrap();
+            }
+            let input = format!(
+                "id,name,vip,at\n9,y,true,2024-05-01T00:00:00Z\n{id},z,true,2024-05-01T00:00:00Z\n"
+            );
+            let report = TableTransfer::new(&MEMBER_COPY, TransferFormat::Csv)
+                .import(&conn, input.as_bytes())
+                .await
+                .unwrap();
+            assert_eq!(1, report.imported);
+            assert_eq!(
+                vec![2],
+                report.failed.iter().map(|e| e.line).collect::<Vec<_>>()
+            );
+        }
+        conn.execute("commit", []).unwrap();
+
+        assert!(
+            TableTransfer::new(&MEMBER_COPY, TransferFormat::Csv)
+                .import(&conn, "id,nope\n1,2\n".as_bytes())
+                .await
+                .is_err()
+        );
+    }
+    static NOTE: CreateTableSql = CreateTableSql {
+        table_name: "transfer_note",
+        fields: &[
+            CreateTableField {
+                name: "id",
+                kind: LogicFieldType::I64,
+                not_null: true,
+            },
+            CreateTableField {
+                name: "tenant_id",
+                kind: LogicFieldType::I64,
+                not_null: true,
+            },
+            CreateTableField {
+                name: "body",
+                kind: LogicFieldType::Text,
+                not_null: true,
+            },
+        ],
+        pkey: &["id"],
+        unikeys: &[],
+        keys: &[],
+        tenant: &["tenant_id"],
+    };
+
+    #[tokio::test]
+    async fn export_import_scoped() {
+        register_tenant_table("transfer_note", &["tenant_id"]);
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        for sql in NOTE.to_owned_sql().sqls(DbType::Sqlite).unwrap() {
+            conn.execute(&sql, []).unwrap();
+        }
+        conn.execute(
+            "insert into transfer_note values (1, 7, 'a'), (2, 8, 'b'), (3, 7, 'c')",
+            [],
+        )
+        .unwrap();
+
+        let transfer = TableTransfer::new(&NOTE, TransferFormat::Csv).batch_size(1);
+        assert!(transfer.export(&conn, &mut vec![]).await.is_err());
+
+        let scope = TenantScope::new().column("tenant_id", 7);
+        let mut out = vec![];
+        let transfer = transfer.scope(&scope);
+        assert_eq!(2, transfer.export(&conn, &mut out).await.unwrap());
+        assert_eq!(
+            "id,tenant_id,body\n1,7,a\n3,7,c\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        // rows without the tenant column get the scope, rows of other tenants fail
+        conn.execute("delete from transfer_note", []).unwrap();
+        let input = "id,body\n1,a\n";
+        let report = transfer.import(&conn, input.as_bytes()).await.unwrap();
+        assert_eq!(1, report.imported);
+        let input = "id,tenant_id,body\n2,7,b\n3,8,c\n";
+        let report = transfer.import(&conn, input.as_bytes()).await.unwrap();
+        assert_eq!(1, report.imported);
+        assert_eq!(
+            vec![3],
+            report.failed.iter().map(|e| e.line).collect::<Vec<_>>()
+        );
+        let tenants: Vec<i64> = conn
+            .prepare("select tenant_id from transfer_note order by id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![7, 7], tenants);
+    }
+}
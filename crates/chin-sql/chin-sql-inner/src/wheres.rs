@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use crate::{DbType, PlaceHolderType, SegOrVal, SqlSeg};
+use crate::{ChinSqlError, DbType, FullTextExpr, JsonPath, PlaceHolderType, SegOrVal, SqlSeg};
 
 use super::sql_value::SqlValue;
 
@@ -17,6 +17,84 @@ pub enum ILikeType {
     Fuzzy,
 }
 
+impl ILikeType {
+    // `%`, `_` and `\` of the input match themselves, see `escape '\'` of `Wheres::Like`
+    fn pattern(&self, s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            if matches!(c, '%' | '_' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        match self {
+            ILikeType::Original => escaped,
+            ILikeType::RightFuzzy => format!("{}%", escaped),
+            ILikeType::LeftFuzzy => format!("%{}", escaped),
+            ILikeType::Fuzzy => format!("%{}%", escaped),
+        }
+    }
+}
+
+/// Translates a like pattern with `\` escapes into a sqlite glob pattern.
+fn glob_pattern(like: &str) -> String {
+    let mut glob = String::with_capacity(like.len());
+    let mut chars = like.chars();
+    while let Some(c) = chars.next() {
+        let literal = match c {
+            '%' => {
+                glob.push('*');
+                continue;
+            }
+            '_' => {
+                glob.push('?');
+                continue;
+            }
+            '\\' => match chars.next() {
+                Some(c) => c,
+                None => break,
+            },
+            c => c,
+        };
+        match literal {
+            '*' | '?' | '[' => {
+                glob.push('[');
+                glob.push(literal);
+                glob.push(']');
+            }
+            c => glob.push(c),
+        }
+    }
+    glob
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LikeCase {
+    /// `glob` on sqlite, whose `like` ignores the case of ascii letters.
+    Sensitive,
+    /// `ilike` on postgres, sqlite only folds ascii letters.
+    Insensitive,
+    /// Folds all letters, sqlite connections need [`register_unicode_lower`].
+    Unicode,
+}
+
+/// Name of the sqlite function registered by [`register_unicode_lower`].
+pub const UNICODE_LOWER: &str = "chin_lower";
+
+/// Registers `chin_lower(text)`, which lower cases all unicode letters, for
+/// [`LikeCase::Unicode`].
+#[cfg(feature = "sqlite")]
+pub fn register_unicode_lower(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    use rusqlite::functions::FunctionFlags;
+
+    conn.create_scalar_function(
+        UNICODE_LOWER,
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(ctx.get::<Option<String>>(0)?.map(|s| s.to_lowercase())),
+    )
+}
+
 pub struct FilterCount {
     pub check_filter_count: bool,
     pub filter_count: usize,
@@ -59,9 +137,16 @@ pub enum Wheres<'a> {
     }, // key, operator, value
     Raw(Cow<'a, str>),
     SOV(Vec<SegOrVal<'a>>),
-    IIike {
+    Like {
         key: Cow<'a, str>,
-        value: String,
+        pattern: String,
+        case: LikeCase,
+    },
+    FullText(FullTextExpr),
+    Json {
+        path: JsonPath<'a>,
+        operator: Cow<'a, str>,
+        value: SqlValue<'a>,
     },
     None,
 }
@@ -81,7 +166,9 @@ impl<'a> Wheres<'a> {
             } => key.is_empty(),
             Wheres::Raw(cow) => cow.is_empty(),
             Wheres::SOV(seg_or_vals) => seg_or_vals.is_empty(),
-            Wheres::IIike { key, value: _ } => key.is_empty(),
+            Wheres::Like { key, .. } => key.is_empty(),
+            Wheres::FullText(_) => false,
+            Wheres::Json { path, .. } => path.column().is_empty(),
             Wheres::None => true,
         }
     }
@@ -95,20 +182,54 @@ impl<'a> Wheres<'a> {
     }
 
     pub fn ilike<T: AsRef<str>, S: Into<Cow<'a, str>>>(key: S, v: T, exact: ILikeType) -> Self {
+        Self::like_case(key, v, exact, LikeCase::Insensitive)
+    }
+
+    pub fn like<T: AsRef<str>, S: Into<Cow<'a, str>>>(key: S, v: T, exact: ILikeType) -> Self {
+        Self::like_case(key, v, exact, LikeCase::Sensitive)
+    }
+
+    /// Wildcards of `v` are escaped, an empty `v` matches everything. Use
+    /// [`Wheres::like_pattern`] for a pattern with wildcards of its own.
+    pub fn like_case<T: AsRef<str>, S: Into<Cow<'a, str>>>(
+        key: S,
+        v: T,
+        exact: ILikeType,
+        case: LikeCase,
+    ) -> Self {
         let s = v.as_ref();
         if s.is_empty() {
             return Wheres::None;
         }
-        Self::IIike {
+        Self::Like {
             key: key.into(),
-            value: match exact {
-                ILikeType::Original => v.as_ref().into(),
-                ILikeType::RightFuzzy => format!("{}%", v.as_ref()),
-                ILikeType::LeftFuzzy => format!("%{}", v.as_ref()),
-                ILikeType::Fuzzy => format!("%{}%", v.as_ref()),
-            },
+            pattern: exact.pattern(s),
+            case,
         }
     }
+
+    /// `pattern` is used as is, `%` and `_` are wildcards and `\` escapes them.
+    pub fn like_pattern<T: Into<String>, S: Into<Cow<'a, str>>>(
+        key: S,
+        pattern: T,
+        case: LikeCase,
+    ) -> Self {
+        Self::Like {
+            key: key.into(),
+            pattern: pattern.into(),
+            case,
+        }
+    }
+
+    pub fn between<S: Into<Cow<'a, str>>, T: Into<SqlValue<'a>>>(key: S, low: T, high: T) -> Self {
+        Self::SOV(vec![
+            SegOrVal::Str(key.into()),
+            " between ".into(),
+            SegOrVal::val(low),
+            " and ".into(),
+            SegOrVal::val(high),
+        ])
+    }
     pub fn is_null<S: Into<Cow<'a, str>>>(key: S) -> Self {
         Self::compare_str(key.into(), "is", "null")
     }
@@ -175,27 +296,39 @@ impl<'a> Wheres<'a> {
         Self::In(key.into(), values.into_iter().map(|e| e.into()).collect())
     }
 
+    pub fn not_in<T: Into<SqlValue<'a>>, S: Into<Cow<'a, str>>>(key: S, values: Vec<T>) -> Self {
+        Self::not(Self::r#in(key, values))
+    }
+
     pub fn none() -> Self {
         Self::None
     }
 
-    pub fn build(self, db_type: DbType, value_type: &mut PlaceHolderType) -> Option<SqlSeg<'a>> {
+    /// `None` if there is no filter.
+    pub fn build(
+        self,
+        db_type: DbType,
+        value_type: &mut PlaceHolderType,
+    ) -> Result<Option<SqlSeg<'a>>, ChinSqlError> {
         let mut seg = String::new();
         let mut values: Vec<SqlValue<'a>> = Vec::new();
 
         match self {
             Wheres::Conj(op, fs) => {
-                let vs: Vec<String> = fs
-                    .into_iter()
-                    .filter_map(|e| {
-                        e.build(db_type, value_type).map(|ss| {
-                            values.extend(ss.values);
+                let mut vs: Vec<String> = Vec::new();
+                for e in fs {
+                    let nested = matches!(e, Wheres::Conj(..));
+                    if let Some(ss) = e.build(db_type, value_type)? {
+                        values.extend(ss.values);
+                        vs.push(if nested {
+                            format!("({})", ss.seg)
+                        } else {
                             ss.seg
-                        })
-                    })
-                    .collect();
+                        });
+                    }
+                }
                 if vs.is_empty() {
-                    return None;
+                    return Ok(None);
                 }
                 let op = match op {
                     WhereConjOp::And => " and ",
@@ -219,17 +352,17 @@ impl<'a> Wheres<'a> {
             }
             Wheres::Not(fs) => {
                 seg.push_str(" not ( ");
-                if let Some(ss) = fs.build(db_type, value_type) {
+                if let Some(ss) = fs.build(db_type, value_type)? {
                     seg.push_str(&ss.seg);
                     seg.push(')');
 
                     values.extend(ss.values);
                 } else {
-                    return None;
+                    return Ok(None);
                 }
             }
             Wheres::None => {
-                return None;
+                return Ok(None);
             }
             Wheres::Compare {
                 key,
@@ -265,27 +398,137 @@ impl<'a> Wheres<'a> {
                     }
                 }
             }
-            Wheres::IIike { key, value } => {
-                let ilike = match db_type {
-                    DbType::Sqlite => Self::Compare {
-                        key,
-                        operator: "like".into(),
-                        value: value.into(),
-                    },
-                    DbType::Postgres => Self::Compare {
-                        key,
-                        operator: "ilike".into(),
-                        value: value.into(),
-                    },
+            Wheres::Json {
+                path,
+                operator,
+                value,
+            } => {
+                seg.push_str(&path.compare_sql(db_type, &value));
+                seg.push(' ');
+                seg.push_str(operator.as_ref());
+                seg.push(' ');
+                seg.push_str(&value_type.next_ph());
+                values.push(value);
+            }
+            Wheres::FullText(expr) => {
+                let SqlSeg { seg: s, values: v } = expr.to_sql_seg(db_type, value_type)?;
+                seg.push_str(s.as_str());
+                values.extend(v);
+            }
+            Wheres::Like { key, pattern, case } => {
+                let (key, operator, pattern) = match (db_type, case) {
+                    (DbType::Sqlite, LikeCase::Unicode) => (
+                        format!("{UNICODE_LOWER}({key})").into(),
+                        "like",
+                        pattern.to_lowercase(),
+                    ),
+                    (DbType::Sqlite, LikeCase::Sensitive) => (key, "glob", glob_pattern(&pattern)),
+                    (
+                        DbType::Postgres | DbType::DuckDb,
+                        LikeCase::Insensitive | LikeCase::Unicode,
+                    ) => (key, "ilike", pattern),
+                    _ => (key, "like", pattern),
                 };
-                let s = ilike.build(db_type, value_type);
-                if let Some(SqlSeg { seg: s, values: v }) = s {
-                    seg.push_str(s.as_str());
-                    values.extend(v);
+                seg.push_str(&key);
+                seg.push(' ');
+                seg.push_str(operator);
+                seg.push(' ');
+                seg.push_str(&value_type.next_ph());
+                if operator != "glob" {
+                    seg.push_str(" escape '\\'");
                 }
+                values.push(SqlValue::Str(Cow::Owned(pattern)));
             }
         }
 
-        Some(SqlSeg::of(seg, values))
+        Ok(Some(SqlSeg::of(seg, values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DbType, ILikeType, IntoSqlSeg, SqlBuilder, SqlValue, Wheres};
+
+    #[test]
+    fn like_escape() {
+        let seg = SqlBuilder::new()
+            .r#where(Wheres::ilike("name", "50%_a\\", ILikeType::Fuzzy))
+            .into_sql_seg(DbType::Postgres)
+            .unwrap();
+        assert_eq!("where name ilike $1 escape '\\'", seg.seg.trim());
+        assert!(matches!(&seg.values[0], SqlValue::Str(s) if s == "%50\\%\\_a\\\\%"));
+        let seg = SqlBuilder::new()
+            .r#where(Wheres::ilike("name", "a", ILikeType::Fuzzy))
+            .into_sql_seg(DbType::DuckDb)
+            .unwrap();
+        assert_eq!("where name ilike $1 escape '\\'", seg.seg.trim());
+        let seg = SqlBuilder::new()
+            .r#where(Wheres::like("name", "a*_", ILikeType::RightFuzzy))
+            .into_sql_seg(DbType::Sqlite)
+            .unwrap();
+        assert_eq!("where name glob ?", seg.seg.trim());
+        assert!(matches!(&seg.values[0], SqlValue::Str(s) if s == "a[*]_*"));
+
+        #[cfg(feature = "sqlite")]
+        {
+            let conn = rusqlite::Connection::open_in_memory().unwrap();
+            crate::register_unicode_lower(&conn).unwrap();
+            conn.execute_batch(
+                "create table item (name text);
+                insert into item values ('50% off'), ('500 off'), ('école'), ('École'), ('ÉCOLE'), ('ecole'), ('ECOLE'), ('a*b');",
+            )
+            .unwrap();
+            let count = |wheres: Wheres| {
+                let seg = SqlBuilder::new()
+                    .seg("select count(*) from item")
+                    .r#where(wheres)
+                    .into_sql_seg(DbType::Sqlite)
+                    .unwrap();
+                conn.query_row(&seg.seg, rusqlite::params_from_iter(seg.values), |r| {
+                    r.get::<_, i64>(0)
+                })
+                .unwrap()
+            };
+            assert_eq!(
+                1,
+                count(Wheres::ilike("name", "50%", ILikeType::RightFuzzy))
+            );
+            // ascii folding only
+            assert_eq!(
+                1,
+                count(Wheres::ilike("name", "école", ILikeType::Original))
+            );
+            assert_eq!(
+                2,
+                count(Wheres::ilike("name", "ecole", ILikeType::Original))
+            );
+            assert_eq!(1, count(Wheres::like("name", "ecole", ILikeType::Original)));
+            assert_eq!(1, count(Wheres::like("name", "*", ILikeType::Fuzzy)));
+            assert_eq!(
+                2,
+                count(Wheres::like_pattern(
+                    "name",
+                    "50%",
+                    crate::LikeCase::Sensitive
+                ))
+            );
+            assert_eq!(
+                1,
+                count(Wheres::like_pattern(
+                    "name",
+                    "50\\%%",
+                    crate::LikeCase::Sensitive
+                ))
+            );
+            assert_eq!(
+                3,
+                count(Wheres::like_case(
+                    "name",
+                    "école",
+                    ILikeType::Original,
+                    crate::LikeCase::Unicode
+                ))
+            );
+        }
     }
 }